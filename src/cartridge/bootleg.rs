@@ -0,0 +1,164 @@
+use crate::cartridge::mapper::Mapper;
+
+/// Wisdom Tree carts switch 32 KiB at a time, taking the bank number from
+/// the low byte of the address written to rather than the data.
+pub struct WisdomTree {
+    bank: usize,
+}
+
+impl WisdomTree {
+    pub fn new() -> Self {
+        Self { bank: 0 }
+    }
+}
+
+impl Mapper for WisdomTree {
+    fn rom_banks(&self) -> (usize, usize) {
+        (self.bank * 2, self.bank * 2 + 1)
+    }
+
+    fn write_rom(&mut self, addr: u16, _byte: u8) {
+        if addr < 0x4000 {
+            self.bank = (addr & 0xFF) as usize;
+        }
+    }
+
+    fn read_ram(&self, _addr: u16) -> u8 {
+        0xFF
+    }
+
+    fn write_ram(&mut self, _addr: u16, _byte: u8) -> bool {
+        false
+    }
+
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
+
+    fn load_save_data(&mut self, _data: &[u8]) {}
+}
+
+/// Sachen MMC1/MMC2 banking: a base bank and a mask pick the game on
+/// multicarts, the plain bank register selects within it. Base and mask
+/// can only be changed while the bank register has bits 4 and 5 set.
+pub struct Sachen {
+    base: usize,
+    mask: usize,
+    bank: usize,
+}
+
+impl Sachen {
+    pub fn new() -> Self {
+        Self { base: 0, mask: 0, bank: 1 }
+    }
+
+    fn unlocked(&self) -> bool {
+        self.bank & 0x30 == 0x30
+    }
+}
+
+impl Mapper for Sachen {
+    fn rom_banks(&self) -> (usize, usize) {
+        let base = self.base & self.mask;
+        (base, base | self.bank & !self.mask)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF if self.unlocked() => self.base = byte as usize,
+            0x2000..=0x3FFF => self.bank = (byte as usize).max(1),
+            0x4000..=0x5FFF if self.unlocked() => self.mask = byte as usize,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, _addr: u16) -> u8 {
+        0xFF
+    }
+
+    fn write_ram(&mut self, _addr: u16, _byte: u8) -> bool {
+        false
+    }
+
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
+
+    fn load_save_data(&mut self, _data: &[u8]) {}
+}
+
+/// Rocket Games carts only decode the ROM bank register, taking the whole
+/// byte written to 0x2000–0x3FFF. Bank 0 maps to bank 1 as on MBC1.
+pub struct Rocket {
+    bank: usize,
+}
+
+impl Rocket {
+    pub fn new() -> Self {
+        Self { bank: 1 }
+    }
+}
+
+impl Mapper for Rocket {
+    fn rom_banks(&self) -> (usize, usize) {
+        (0, self.bank)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        if (0x2000..=0x3FFF).contains(&addr) {
+            self.bank = (byte as usize).max(1);
+        }
+    }
+
+    fn read_ram(&self, _addr: u16) -> u8 {
+        0xFF
+    }
+
+    fn write_ram(&mut self, _addr: u16, _byte: u8) -> bool {
+        false
+    }
+
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
+
+    fn load_save_data(&mut self, _data: &[u8]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rocket_selects_full_byte_banks() {
+        let mut rocket = Rocket::new();
+        assert_eq!(rocket.rom_banks(), (0, 1));
+
+        rocket.write_rom(0x2000, 0x85);
+        assert_eq!(rocket.rom_banks(), (0, 0x85));
+
+        rocket.write_rom(0x3FFF, 0x00);
+        assert_eq!(rocket.rom_banks(), (0, 1));
+
+        rocket.write_rom(0x0000, 0x0A);
+        rocket.write_rom(0x4000, 0x03);
+        assert_eq!(rocket.rom_banks(), (0, 1));
+    }
+
+    #[test]
+    fn sachen_base_and_mask_need_unlock() {
+        let mut sachen = Sachen::new();
+        sachen.write_rom(0x0000, 0x10);
+        assert_eq!(sachen.rom_banks(), (0, 1));
+
+        sachen.write_rom(0x2000, 0x30);
+        sachen.write_rom(0x0000, 0x10);
+        sachen.write_rom(0x4000, 0xF0);
+        sachen.write_rom(0x2000, 0x02);
+        assert_eq!(sachen.rom_banks(), (0x10, 0x12));
+
+        // Locked again, so the game can't escape its slice of the multicart.
+        sachen.write_rom(0x0000, 0x20);
+        assert_eq!(sachen.rom_banks(), (0x10, 0x12));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::cartridge::mapper::{load_ram, ram_offset, write_banked, Mapper};
+use crate::cartridge::sensor::{CameraInput, SENSOR_HEIGHT, SENSOR_WIDTH};
+
+const RAM_SIZE: usize = 0x20000;
+const REGISTER_BANK: u8 = 0x10;
+const REGISTER_COUNT: usize = 0x36;
+
+const REG_CONTROL: usize = 0x00;
+const REG_GAIN: usize = 0x01;
+const REG_EXPOSURE_HIGH: usize = 0x02;
+const REG_EXPOSURE_LOW: usize = 0x03;
+const REG_EDGE: usize = 0x04;
+const REG_DITHER: usize = 0x06;
+
+const CONTROL_CAPTURE: u8 = 0x01;
+const GAIN_N: u8 = 0x80;
+const GAIN_2D_EDGE: u8 = 0xE0;
+const EDGE_INVERT: u8 = 0x08;
+
+/// Captured image location in RAM bank 0, as 16 × 14 2bpp tiles.
+const IMAGE_START: usize = 0x0100;
+
+const EDGE_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+
+/// The Pocket Camera: a 128 KiB RAM cartridge whose bank 0x10 holds the
+/// registers of a M64282FP image sensor and its processing pipeline.
+pub struct Camera {
+    ram: Vec<u8>,
+    registers: [u8; REGISTER_COUNT],
+    input: Option<Box<dyn CameraInput>>,
+    ram_enabled: bool,
+    rom_bank: usize,
+    ram_bank: u8,
+    capture_cycles: u32,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            ram: vec![0; RAM_SIZE],
+            registers: [0; REGISTER_COUNT],
+            input: None,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            capture_cycles: 0,
+        }
+    }
+
+    fn exposure(&self) -> u32 {
+        (self.registers[REG_EXPOSURE_HIGH] as u32) << 8 | self.registers[REG_EXPOSURE_LOW] as u32
+    }
+
+    fn start_capture(&mut self) {
+        let n_cycles = if self.registers[REG_GAIN] & GAIN_N != 0 { 0 } else { 2048 };
+        self.capture_cycles = 129792 + n_cycles + 64 * self.exposure();
+        self.registers[REG_CONTROL] |= CONTROL_CAPTURE;
+    }
+
+    fn finish_capture(&mut self) {
+        self.registers[REG_CONTROL] &= !CONTROL_CAPTURE;
+
+        let mut frame = self.input.as_mut()
+            .map(|input| input.frame())
+            .unwrap_or_default();
+        // Inputs are outside our control; pad short frames with black and
+        // cut long ones off rather than trusting the size.
+        frame.resize(SENSOR_WIDTH * SENSOR_HEIGHT, 0);
+        let image = self.process(&frame);
+
+        for y in 0..SENSOR_HEIGHT {
+            for x in 0..SENSOR_WIDTH {
+                let tile = (y / 8) * (SENSOR_WIDTH / 8) + x / 8;
+                let offset = IMAGE_START + tile * 16 + (y % 8) * 2;
+                let bit = 7 - (x % 8);
+                let colour = image[y * SENSOR_WIDTH + x];
+
+                self.ram[offset] = self.ram[offset] & !(1 << bit) | (colour & 0x01) << bit;
+                self.ram[offset + 1] = self.ram[offset + 1] & !(1 << bit) | (colour >> 1) << bit;
+            }
+        }
+    }
+
+    /// Sensor voltage at (x, y) after gain and exposure, clamping at the
+    /// image edges.
+    fn sensor(&self, frame: &[u8], x: isize, y: isize) -> f32 {
+        let x = x.clamp(0, SENSOR_WIDTH as isize - 1) as usize;
+        let y = y.clamp(0, SENSOR_HEIGHT as isize - 1) as usize;
+        let gain = 1.0 + (self.registers[REG_GAIN] & 0x1F) as f32 / 8.0;
+
+        frame[y * SENSOR_WIDTH + x] as f32 * gain * self.exposure() as f32 / 0x1000 as f32
+    }
+
+    /// Runs the sensor pipeline over `frame`, producing 2bpp colour indices.
+    fn process(&self, frame: &[u8]) -> Vec<u8> {
+        let edge = self.registers[REG_GAIN] & GAIN_2D_EDGE == GAIN_2D_EDGE;
+        let ratio = EDGE_RATIOS[(self.registers[REG_EDGE] >> 4 & 0x07) as usize];
+        let invert = self.registers[REG_EDGE] & EDGE_INVERT != 0;
+
+        let mut image = Vec::with_capacity(SENSOR_WIDTH * SENSOR_HEIGHT);
+        for y in 0..SENSOR_HEIGHT as isize {
+            for x in 0..SENSOR_WIDTH as isize {
+                let mut value = self.sensor(frame, x, y);
+                if edge {
+                    let neighbours = self.sensor(frame, x - 1, y)
+                        + self.sensor(frame, x + 1, y)
+                        + self.sensor(frame, x, y - 1)
+                        + self.sensor(frame, x, y + 1);
+                    value += (value * 4.0 - neighbours) * ratio;
+                }
+                if invert {
+                    value = 255.0 - value;
+                }
+
+                let cell = REG_DITHER + ((x as usize & 3) + (y as usize & 3) * 4) * 3;
+                let thresholds = &self.registers[cell..cell + 3];
+                let colour = match value {
+                    v if v < thresholds[0] as f32 => 3,
+                    v if v < thresholds[1] as f32 => 2,
+                    v if v < thresholds[2] as f32 => 1,
+                    _ => 0,
+                };
+                image.push(colour);
+            }
+        }
+
+        image
+    }
+}
+
+impl Mapper for Camera {
+    fn rom_banks(&self) -> (usize, usize) {
+        (0, self.rom_bank)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (byte & 0x3F) as usize,
+            0x4000..=0x5FFF => self.ram_bank = byte & 0x1F,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if self.ram_bank & REGISTER_BANK != 0 {
+            return match addr as usize & 0x7F {
+                REG_CONTROL => self.registers[REG_CONTROL] & 0x07,
+                _ => 0x00,
+            };
+        }
+
+        ram_offset(&self.ram, self.ram_bank as usize, addr).map_or(0xFF, |i| self.ram[i])
+    }
+
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        if self.ram_bank & REGISTER_BANK != 0 {
+            let reg = addr as usize & 0x7F;
+            if reg == REG_CONTROL {
+                self.registers[REG_CONTROL] = byte & 0x06 | self.registers[REG_CONTROL] & CONTROL_CAPTURE;
+                if byte & CONTROL_CAPTURE != 0 {
+                    self.start_capture();
+                }
+            } else if reg < REGISTER_COUNT {
+                self.registers[reg] = byte;
+            }
+            return false;
+        }
+
+        self.ram_enabled && write_banked(&mut self.ram, self.ram_bank as usize, addr, byte)
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn tick(&mut self, cycles: u32) -> bool {
+        if self.capture_cycles == 0 {
+            return false;
+        }
+
+        self.capture_cycles = self.capture_cycles.saturating_sub(cycles);
+        if self.capture_cycles == 0 {
+            // The picture lands in battery-backed RAM.
+            self.finish_capture();
+            return true;
+        }
+        false
+    }
+
+    fn set_camera_input(&mut self, input: Box<dyn CameraInput>) {
+        self.input = Some(input);
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::sensor::StillImage;
+
+    fn capture(camera: &mut Camera) {
+        camera.write_rom(0x4000, REGISTER_BANK);
+        camera.write_ram(REG_CONTROL as u16, CONTROL_CAPTURE);
+        camera.tick(u32::MAX);
+        assert_eq!(camera.read_ram(REG_CONTROL as u16) & CONTROL_CAPTURE, 0);
+    }
+
+    fn set(camera: &mut Camera, reg: usize, byte: u8) {
+        camera.write_rom(0x4000, REGISTER_BANK);
+        camera.write_ram(reg as u16, byte);
+    }
+
+    fn set_exposure(camera: &mut Camera, exposure: u16) {
+        set(camera, REG_EXPOSURE_HIGH, (exposure >> 8) as u8);
+        set(camera, REG_EXPOSURE_LOW, exposure as u8);
+    }
+
+    /// Gives dither cells whose `x & 3` passes `columns` the thresholds `t`.
+    fn set_dither(camera: &mut Camera, columns: impl Fn(usize) -> bool, t: [u8; 3]) {
+        for cell in (0..16).filter(|cell| columns(cell & 3)) {
+            for (i, &threshold) in t.iter().enumerate() {
+                set(camera, REG_DITHER + cell * 3 + i, threshold);
+            }
+        }
+    }
+
+    /// A camera looking at a PGM image loaded through `StillImage`.
+    fn looking_at(name: &str, pgm: &[u8]) -> Camera {
+        let path = std::env::temp_dir().join(format!("immolator-{name}-{}.pgm", std::process::id()));
+        std::fs::write(&path, pgm).unwrap();
+        let image = StillImage::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut camera = Camera::new();
+        camera.set_camera_input(Box::new(image));
+        set_exposure(&mut camera, 0x1000);
+        set_dither(&mut camera, |_| true, [0x40, 0x80, 0xC0]);
+        camera
+    }
+
+    /// The two bitplane bytes of the first row of `tile`.
+    fn tile_row(camera: &Camera, tile: usize) -> (u8, u8) {
+        let offset = IMAGE_START + tile * 16;
+        (camera.ram[offset], camera.ram[offset + 1])
+    }
+
+    #[test]
+    fn exposure_scales_the_image() {
+        let mut camera = looking_at("exposure", b"P5 1 1 255\n\x80");
+        for (exposure, row) in [
+            (0x0400, (0xFF, 0xFF)),
+            (0x0800, (0x00, 0xFF)),
+            (0x1000, (0xFF, 0x00)),
+            (0x2000, (0x00, 0x00)),
+        ] {
+            set_exposure(&mut camera, exposure);
+            capture(&mut camera);
+            assert_eq!(tile_row(&camera, 0), row, "exposure {exposure:#06x}");
+        }
+    }
+
+    #[test]
+    fn dither_matrix_thresholds_each_cell() {
+        let mut camera = looking_at("dither", b"P5 1 1 255\n\x80");
+        set_dither(&mut camera, |column| column >= 2, [0x90, 0xA0, 0xB0]);
+        capture(&mut camera);
+
+        // Columns 0 and 1 of each four come out as colour 1, 2 and 3 as colour 3.
+        assert_eq!(tile_row(&camera, 0), (0xFF, 0x33));
+    }
+
+    #[test]
+    fn edge_enhancement_sharpens_and_inverts() {
+        // Black on the left half, mid grey on the right.
+        let mut camera = looking_at("edge", b"P5 2 1 255\n\x00\x80");
+        capture(&mut camera);
+        assert_eq!(tile_row(&camera, 7), (0xFF, 0xFF));
+        assert_eq!(tile_row(&camera, 8), (0xFF, 0x00));
+
+        // Ratio 1.0 lifts the first grey column past every threshold.
+        set(&mut camera, REG_GAIN, GAIN_2D_EDGE);
+        set(&mut camera, REG_EDGE, 0x20);
+        capture(&mut camera);
+        assert_eq!(tile_row(&camera, 7), (0xFF, 0xFF));
+        assert_eq!(tile_row(&camera, 8), (0x7F, 0x00));
+
+        set(&mut camera, REG_GAIN, 0);
+        set(&mut camera, REG_EDGE, EDGE_INVERT);
+        capture(&mut camera);
+        assert_eq!(tile_row(&camera, 7), (0x00, 0x00));
+        assert_eq!(tile_row(&camera, 8), (0x00, 0xFF));
+    }
+
+    #[test]
+    fn capture_stays_busy_for_its_exposure() {
+        for (gain, exposure, cycles) in [
+            (0, 0x0000, 129792 + 2048),
+            (0, 0x0100, 129792 + 2048 + 64 * 0x100),
+            (GAIN_N, 0x0100, 129792 + 64 * 0x100),
+        ] {
+            let mut camera = Camera::new();
+            set(&mut camera, REG_GAIN, gain);
+            set_exposure(&mut camera, exposure);
+            set(&mut camera, REG_CONTROL, CONTROL_CAPTURE);
+
+            assert!(!camera.tick(cycles - 1));
+            assert_eq!(camera.read_ram(REG_CONTROL as u16) & CONTROL_CAPTURE, CONTROL_CAPTURE);
+            assert!(camera.tick(1));
+            assert_eq!(camera.read_ram(REG_CONTROL as u16) & CONTROL_CAPTURE, 0);
+        }
+    }
+
+    #[test]
+    fn wrong_size_frames_are_padded_or_cut() {
+        for len in [0, 100, SENSOR_WIDTH * SENSOR_HEIGHT + 100] {
+            let mut camera = Camera::new();
+            camera.set_camera_input(Box::new(move || vec![0xFF; len]));
+            capture(&mut camera);
+        }
+    }
+}
@@ -0,0 +1,533 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cartridge::bootleg::{Rocket, Sachen, WisdomTree};
+use crate::cartridge::camera::Camera;
+use crate::cartridge::huc1::HuC1;
+use crate::cartridge::huc3::HuC3;
+use crate::cartridge::mapper::{Mapper, RomOnly};
+use crate::cartridge::mbc3::Mbc3;
+use crate::cartridge::mbc5::Mbc5;
+use crate::cartridge::mbc7::Mbc7;
+use crate::cartridge::mmm01::Mmm01;
+use crate::cartridge::save::SaveFile;
+use crate::cartridge::{CameraInput, RtcMode, RumbleEvent, TiltInput};
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+
+const HEADER_TITLE: usize = 0x134;
+const HEADER_CGB_FLAG: usize = 0x143;
+const HEADER_CARTRIDGE_TYPE: usize = 0x147;
+const HEADER_RAM_SIZE: usize = 0x149;
+const HEADER_END: usize = 0x150;
+
+/// MMM01 compilations keep the menu, and the header describing the whole
+/// cartridge, in the last 32 KiB of ROM.
+const MMM01_MENU_SIZE: usize = 2 * ROM_BANK_SIZE;
+
+/// Mapper hardware a cartridge is wired to, which for unlicensed carts need
+/// not match the cartridge type in the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapperKind {
+    RomOnly,
+    Mbc3,
+    Mbc5,
+    Mbc7,
+    HuC1,
+    HuC3,
+    Camera,
+    Mmm01,
+    WisdomTree,
+    Sachen,
+    Rocket,
+}
+
+impl MapperKind {
+    fn from_cartridge_type(cartridge_type: u8) -> Option<Self> {
+        match cartridge_type {
+            0x00 | 0x08 | 0x09 => Some(Self::RomOnly),
+            0x0B..=0x0D => Some(Self::Mmm01),
+            0x0F..=0x13 => Some(Self::Mbc3),
+            0x19..=0x1E => Some(Self::Mbc5),
+            0x22 => Some(Self::Mbc7),
+            0xFC => Some(Self::Camera),
+            0xFE => Some(Self::HuC3),
+            0xFF => Some(Self::HuC1),
+            _ => None,
+        }
+    }
+
+    /// Parses the name used for this mapper in override files.
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "rom-only" => Some(Self::RomOnly),
+            "mbc3" => Some(Self::Mbc3),
+            "mbc5" => Some(Self::Mbc5),
+            "mbc7" => Some(Self::Mbc7),
+            "huc1" => Some(Self::HuC1),
+            "huc3" => Some(Self::HuC3),
+            "camera" => Some(Self::Camera),
+            "mmm01" => Some(Self::Mmm01),
+            "wisdom-tree" => Some(Self::WisdomTree),
+            "sachen" => Some(Self::Sachen),
+            "rocket" => Some(Self::Rocket),
+            _ => None,
+        }
+    }
+}
+
+/// Mapper overrides keyed by the CRC-32 of the ROM image, as computed by
+/// `rom_crc32`.
+pub type MapperOverrides = HashMap<u32, MapperKind>;
+
+/// Where the mapper overrides for the ROM at `rom_path` live: one file
+/// shared by every ROM in its directory.
+pub fn overrides_path(rom_path: &Path) -> PathBuf {
+    rom_path.with_file_name("mappers.txt")
+}
+
+/// Reads mapper overrides from `path`, one `<CRC-32 in hex> <mapper>` pair
+/// per line with `#` starting a comment. No file means no overrides.
+pub fn read_overrides(path: &Path) -> io::Result<MapperOverrides> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(MapperOverrides::new()),
+        Err(e) => return Err(e),
+    };
+    parse_overrides(&text)
+        .map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {msg}", path.display())))
+}
+
+fn parse_overrides(text: &str) -> Result<MapperOverrides, String> {
+    let mut overrides = MapperOverrides::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let Some(crc) = fields.next() else { continue };
+
+        let crc = u32::from_str_radix(crc.trim_start_matches("0x"), 16)
+            .map_err(|_| format!("line {}: bad checksum {crc:?}", n + 1))?;
+        let kind = fields.next()
+            .and_then(MapperKind::from_name)
+            .ok_or_else(|| format!("line {}: missing or unknown mapper", n + 1))?;
+        if fields.next().is_some() {
+            return Err(format!("line {}: trailing text", n + 1));
+        }
+        overrides.insert(crc, kind);
+    }
+    Ok(overrides)
+}
+
+/// Dumps whose header doesn't describe their mapper, by CRC-32. Consulted
+/// after the caller's overrides. Entries must come from a ROM database
+/// such as No-Intro; a checksum that merely looks right would silently
+/// misload some other game.
+const KNOWN_MAPPERS: &[(u32, MapperKind)] = &[];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CartridgeError {
+    /// The image is too small to contain a header.
+    Truncated,
+    UnsupportedType(u8),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "ROM is too small to contain a cartridge header"),
+            Self::UnsupportedType(t) => write!(f, "unsupported cartridge type {t:#04X}"),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
+pub struct Cartridge {
+    rom: Vec<u8>,
+    title: String,
+    cartridge_type: u8,
+    mapper: Box<dyn Mapper>,
+    save_file: Option<SaveFile>,
+}
+
+impl Cartridge {
+    pub fn new(rom: Vec<u8>) -> Result<Self, CartridgeError> {
+        Self::with_overrides(rom, &MapperOverrides::new())
+    }
+
+    /// Loads `rom`, using the mapper from `overrides` or the built-in list
+    /// of known dumps if its checksum is listed there, and the header's
+    /// cartridge type otherwise.
+    pub fn with_overrides(rom: Vec<u8>, overrides: &MapperOverrides) -> Result<Self, CartridgeError> {
+        let kind = select_mapper(&rom, overrides, KNOWN_MAPPERS)?;
+        Ok(Self::with_mapper(rom, kind))
+    }
+
+    /// Loads `rom` with the given mapper, whatever its header says.
+    pub fn with_mapper(mut rom: Vec<u8>, kind: MapperKind) -> Self {
+        rom.resize(rom.len().max(HEADER_END), 0xFF);
+
+        let header = &rom[header_offset(&rom)..];
+        let cartridge_type = header[HEADER_CARTRIDGE_TYPE];
+        let ram_size = ram_size(header[HEADER_RAM_SIZE]);
+        let title = header[HEADER_TITLE..HEADER_CGB_FLAG]
+            .iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| b as char)
+            .collect();
+
+        rom.resize(rom.len().max(2 * ROM_BANK_SIZE).next_multiple_of(ROM_BANK_SIZE), 0xFF);
+
+        let mapper: Box<dyn Mapper> = match kind {
+            MapperKind::RomOnly => Box::new(RomOnly::new(ram_size)),
+            MapperKind::Mbc3 => Box::new(Mbc3::new(ram_size, matches!(cartridge_type, 0x0F | 0x10))),
+            MapperKind::Mbc5 => Box::new(Mbc5::new(ram_size, matches!(cartridge_type, 0x1C..=0x1E))),
+            MapperKind::Mbc7 => Box::new(Mbc7::new()),
+            MapperKind::HuC1 => Box::new(HuC1::new(ram_size)),
+            MapperKind::HuC3 => Box::new(HuC3::new(ram_size)),
+            MapperKind::Camera => Box::new(Camera::new()),
+            MapperKind::Mmm01 => Box::new(Mmm01::new(rom.len() / ROM_BANK_SIZE, ram_size)),
+            MapperKind::WisdomTree => Box::new(WisdomTree::new()),
+            MapperKind::Sachen => Box::new(Sachen::new()),
+            MapperKind::Rocket => Box::new(Rocket::new()),
+        };
+
+        Self { rom, title, cartridge_type, mapper, save_file: None }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn has_battery(&self) -> bool {
+        matches!(self.cartridge_type, 0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFC | 0xFE | 0xFF)
+    }
+
+    fn rom_bank(&self, bank: usize) -> &[u8] {
+        let start = bank % (self.rom.len() / ROM_BANK_SIZE) * ROM_BANK_SIZE;
+        &self.rom[start..start + ROM_BANK_SIZE]
+    }
+
+    pub fn rom_bank_00(&self) -> &[u8] {
+        self.rom_bank(self.mapper.rom_banks().0)
+    }
+
+    pub fn rom_bank_01_nn(&self) -> &[u8] {
+        self.rom_bank(self.mapper.rom_banks().1)
+    }
+
+    pub fn ext_ram(&self) -> &[u8] {
+        self.mapper.ram()
+    }
+
+    /// Reads from 0x0000–0x7FFF.
+    pub fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        if addr < ROM_BANK_SIZE {
+            self.rom_bank_00()[addr]
+        } else {
+            self.rom_bank_01_nn()[addr - ROM_BANK_SIZE]
+        }
+    }
+
+    pub fn write_rom(&mut self, addr: u16, byte: u8) {
+        self.mapper.write_rom(addr, byte);
+    }
+
+    /// Reads from 0xA000–0xBFFF, `addr` relative to 0xA000.
+    pub fn read_ram(&self, addr: u16) -> u8 {
+        self.mapper.read_ram(addr)
+    }
+
+    pub fn write_ram(&mut self, addr: u16, byte: u8) {
+        if self.mapper.write_ram(addr, byte) {
+            self.mark_dirty();
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        if self.mapper.tick(cycles) {
+            self.mark_dirty();
+        }
+    }
+
+    fn mark_dirty(&mut self) {
+        if let Some(save_file) = &mut self.save_file {
+            save_file.mark_dirty();
+        }
+    }
+
+    pub fn set_rtc_mode(&mut self, mode: RtcMode) {
+        self.mapper.set_rtc_mode(mode);
+    }
+
+    pub fn rumble(&self) -> bool {
+        self.mapper.rumble()
+    }
+
+    /// Rumble motor changes since the last call, for frontends to forward to
+    /// a force-feedback device.
+    pub fn take_rumble_events(&mut self) -> Vec<RumbleEvent> {
+        self.mapper.take_rumble_events()
+    }
+
+    pub fn ir_led(&self) -> bool {
+        self.mapper.ir_led()
+    }
+
+    pub fn set_ir_input(&mut self, light: bool) {
+        self.mapper.set_ir_input(light);
+    }
+
+    pub fn speaker_tone(&self) -> Option<u8> {
+        self.mapper.speaker_tone()
+    }
+
+    pub fn set_tilt_input(&mut self, input: impl TiltInput + 'static) {
+        self.mapper.set_tilt_input(Box::new(input));
+    }
+
+    pub fn set_camera_input(&mut self, input: impl CameraInput + 'static) {
+        self.mapper.set_camera_input(Box::new(input));
+    }
+
+    pub fn save_data(&self) -> Vec<u8> {
+        self.mapper.save_data()
+    }
+
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        self.mapper.load_save_data(data);
+    }
+
+    /// Backs battery-backed state with the file at `path`, loading it if it
+    /// exists. Written back by `flush` and `close`, or failing those when
+    /// the cartridge is dropped. Does nothing for cartridges without a
+    /// battery.
+    pub fn attach_save_file(&mut self, path: impl Into<PathBuf>) -> io::Result<()> {
+        if !self.has_battery() {
+            return Ok(());
+        }
+
+        let save_file = SaveFile::new(path.into());
+        if let Some(data) = save_file.read()? {
+            self.load_save_data(&data);
+        }
+        self.save_file = Some(save_file);
+        Ok(())
+    }
+
+    /// Writes battery-backed state to the save file if it changed since the
+    /// last write. Cheap when nothing did, so frontends can call it every
+    /// frame to keep a crash from losing progress.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.save_file {
+            Some(save_file) if save_file.is_dirty() => save_file.write(&self.mapper.save_data()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the save file one last time and detaches it. Always written,
+    /// so clocks are saved with the time we left at.
+    pub fn close(&mut self) -> io::Result<()> {
+        match self.save_file.take() {
+            Some(mut save_file) => save_file.write(&self.mapper.save_data()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Cartridge {
+    fn drop(&mut self) {
+        // Nobody is left to report a failure to; `close` first to see it.
+        let _ = self.close();
+    }
+}
+
+fn select_mapper(rom: &[u8], overrides: &MapperOverrides, known: &[(u32, MapperKind)]) -> Result<MapperKind, CartridgeError> {
+    if rom.len() < HEADER_END {
+        return Err(CartridgeError::Truncated);
+    }
+
+    let crc = rom_crc32(rom);
+    if let Some(&kind) = overrides.get(&crc) {
+        return Ok(kind);
+    }
+    if let Some(&(_, kind)) = known.iter().find(|&&(c, _)| c == crc) {
+        return Ok(kind);
+    }
+
+    let cartridge_type = rom[header_offset(rom) + HEADER_CARTRIDGE_TYPE];
+    // A ROM-only cartridge can't hold more than 32 KiB, so anything bigger
+    // is a Wisdom Tree cart that didn't bother with a header.
+    if cartridge_type == 0x00 && rom.len() > 2 * ROM_BANK_SIZE {
+        return Ok(MapperKind::WisdomTree);
+    }
+    MapperKind::from_cartridge_type(cartridge_type).ok_or(CartridgeError::UnsupportedType(cartridge_type))
+}
+
+fn ram_size(code: u8) -> usize {
+    match code {
+        0x01 => 0x800,
+        0x02 => 0x2000,
+        0x03 => 0x8000,
+        0x04 => 0x20000,
+        0x05 => 0x10000,
+        _ => 0,
+    }
+}
+
+fn header_offset(rom: &[u8]) -> usize {
+    let menu = rom.len().saturating_sub(MMM01_MENU_SIZE);
+    if rom.len() > MMM01_MENU_SIZE && matches!(rom[menu + HEADER_CARTRIDGE_TYPE], 0x0B..=0x0D) {
+        menu
+    } else {
+        0
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { crc >> 1 ^ 0xEDB88320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 of a ROM image, the checksum ROM databases identify dumps by.
+pub fn rom_crc32(rom: &[u8]) -> u32 {
+    !rom.iter().fold(!0, |crc, &b| CRC32_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ crc >> 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(cartridge_type: u8, len: usize) -> Vec<u8> {
+        let mut rom = vec![0; len];
+        rom[HEADER_CARTRIDGE_TYPE] = cartridge_type;
+        rom
+    }
+
+    fn is_dirty(cartridge: &Cartridge) -> bool {
+        cartridge.save_file.as_ref().is_some_and(|f| f.is_dirty())
+    }
+
+    #[test]
+    fn known_dumps_override_the_header() {
+        let rom = rom(0x19, 4 * ROM_BANK_SIZE);
+        let known = [(rom_crc32(&rom), MapperKind::Sachen)];
+        assert_eq!(select_mapper(&rom, &MapperOverrides::new(), &known), Ok(MapperKind::Sachen));
+        assert_eq!(select_mapper(&rom, &MapperOverrides::new(), &[]), Ok(MapperKind::Mbc5));
+    }
+
+    #[test]
+    fn caller_overrides_win_over_known_dumps() {
+        let rom = rom(0x19, 4 * ROM_BANK_SIZE);
+        let known = [(rom_crc32(&rom), MapperKind::Sachen)];
+        let overrides = MapperOverrides::from([(rom_crc32(&rom), MapperKind::Rocket)]);
+        assert_eq!(select_mapper(&rom, &overrides, &known), Ok(MapperKind::Rocket));
+    }
+
+    #[test]
+    fn header_fallbacks() {
+        let none = MapperOverrides::new();
+        assert_eq!(select_mapper(&rom(0x00, 2 * ROM_BANK_SIZE), &none, &[]), Ok(MapperKind::RomOnly));
+        assert_eq!(select_mapper(&rom(0x00, 8 * ROM_BANK_SIZE), &none, &[]), Ok(MapperKind::WisdomTree));
+        assert_eq!(select_mapper(&rom(0x01, 2 * ROM_BANK_SIZE), &none, &[]), Err(CartridgeError::UnsupportedType(0x01)));
+        assert_eq!(select_mapper(&[0; 0x100], &none, &[]), Err(CartridgeError::Truncated));
+    }
+
+    #[test]
+    fn parses_override_files() {
+        let overrides = parse_overrides("# bootlegs\n\n0x1234ABCD sachen\ndeadbeef  Rocket  # comment\n").unwrap();
+        assert_eq!(overrides, MapperOverrides::from([
+            (0x1234ABCD, MapperKind::Sachen),
+            (0xDEADBEEF, MapperKind::Rocket),
+        ]));
+
+        assert!(parse_overrides("xyz sachen").is_err());
+        assert!(parse_overrides("1234").is_err());
+        assert!(parse_overrides("1234 mbc9").is_err());
+        assert!(parse_overrides("1234 sachen rocket").is_err());
+    }
+
+    #[test]
+    fn sachen_cartridge_loads_from_overrides() {
+        let mut rom = rom(0x19, 0x20 * ROM_BANK_SIZE);
+        rom[0x10 * ROM_BANK_SIZE] = 0x10;
+        rom[0x12 * ROM_BANK_SIZE] = 0x12;
+        let overrides = parse_overrides(&format!("{:08x} sachen", rom_crc32(&rom))).unwrap();
+        let mut cartridge = Cartridge::with_overrides(rom, &overrides).unwrap();
+
+        cartridge.write_rom(0x2000, 0x30);
+        cartridge.write_rom(0x0000, 0x10);
+        cartridge.write_rom(0x4000, 0xF0);
+        cartridge.write_rom(0x2000, 0x02);
+        assert_eq!(cartridge.read_rom(0x0000), 0x10);
+        assert_eq!(cartridge.read_rom(0x4000), 0x12);
+    }
+
+    #[test]
+    fn rocket_cartridge_reads_selected_bank() {
+        let mut rom = rom(0x00, 4 * ROM_BANK_SIZE);
+        rom[3 * ROM_BANK_SIZE] = 0x33;
+        let mut cartridge = Cartridge::with_mapper(rom, MapperKind::Rocket);
+        cartridge.write_rom(0x2000, 3);
+        assert_eq!(cartridge.read_rom(0x4000), 0x33);
+    }
+
+    #[test]
+    fn only_real_changes_dirty_the_save() {
+        let mut rom = rom(0x1B, 4 * ROM_BANK_SIZE);
+        rom[HEADER_RAM_SIZE] = 0x02;
+        let path = std::env::temp_dir().join(format!("immolator-dirty-{}.sav", std::process::id()));
+        let mut cartridge = Cartridge::new(rom).unwrap();
+        cartridge.attach_save_file(&path).unwrap();
+
+        cartridge.write_ram(0x0000, 0x12);
+        assert!(!is_dirty(&cartridge), "RAM is disabled");
+
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x0000, 0x00);
+        assert!(!is_dirty(&cartridge), "RAM already held that value");
+
+        cartridge.write_ram(0x0000, 0x12);
+        assert!(is_dirty(&cartridge));
+        cartridge.flush().unwrap();
+        assert!(!is_dirty(&cartridge));
+        assert_eq!(std::fs::read(&path).unwrap()[0], 0x12);
+
+        cartridge.close().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn flush_writes_only_dirty_saves() {
+        let mut rom = rom(0x1B, 4 * ROM_BANK_SIZE);
+        rom[HEADER_RAM_SIZE] = 0x02;
+        let path = std::env::temp_dir().join(format!("immolator-flush-{}.sav", std::process::id()));
+        let mut cartridge = Cartridge::new(rom).unwrap();
+        cartridge.attach_save_file(&path).unwrap();
+
+        cartridge.flush().unwrap();
+        assert!(!path.exists(), "clean saves aren't written");
+
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0x1FFF, 0x34);
+        cartridge.flush().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[0x1FFF], 0x34);
+
+        cartridge.close().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
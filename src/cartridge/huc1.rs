@@ -0,0 +1,97 @@
+use crate::cartridge::mapper::{load_ram, ram_offset, write_banked, Mapper};
+
+const MODE_IR: u8 = 0x0E;
+
+pub struct HuC1 {
+    ram: Vec<u8>,
+    ir_mode: bool,
+    rom_bank: usize,
+    ram_bank: usize,
+    ir_led: bool,
+    ir_input: bool,
+}
+
+impl HuC1 {
+    pub fn new(ram_size: usize) -> Self {
+        Self {
+            ram: vec![0; ram_size],
+            ir_mode: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            ir_led: false,
+            ir_input: false,
+        }
+    }
+}
+
+impl Mapper for HuC1 {
+    fn rom_banks(&self) -> (usize, usize) {
+        (0, self.rom_bank)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ir_mode = byte & 0x0F == MODE_IR,
+            0x2000..=0x3FFF => self.rom_bank = ((byte & 0x3F) as usize).max(1),
+            0x4000..=0x5FFF => self.ram_bank = (byte & 0x03) as usize,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if self.ir_mode {
+            return 0xC0 | self.ir_input as u8;
+        }
+
+        ram_offset(&self.ram, self.ram_bank, addr).map_or(0xFF, |i| self.ram[i])
+    }
+
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        if self.ir_mode {
+            self.ir_led = byte & 0x01 != 0;
+            return false;
+        }
+
+        write_banked(&mut self.ram, self.ram_bank, addr, byte)
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ir_led(&self) -> bool {
+        self.ir_led
+    }
+
+    fn set_ir_input(&mut self, light: bool) {
+        self.ir_input = light;
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ir_mode_takes_over_the_ram_window() {
+        let mut huc1 = HuC1::new(0x2000);
+        huc1.write_ram(0x0000, 0x55);
+
+        huc1.write_rom(0x0000, MODE_IR);
+        assert_eq!(huc1.read_ram(0x0000), 0xC0);
+        huc1.set_ir_input(true);
+        assert_eq!(huc1.read_ram(0x0000), 0xC1);
+
+        assert!(!huc1.write_ram(0x0000, 0x01));
+        assert!(huc1.ir_led());
+        huc1.write_ram(0x0000, 0x00);
+        assert!(!huc1.ir_led());
+
+        huc1.write_rom(0x0000, 0x0A);
+        assert_eq!(huc1.read_ram(0x0000), 0x55);
+    }
+}
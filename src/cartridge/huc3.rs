@@ -0,0 +1,434 @@
+use byteorder::{ByteOrder, LittleEndian};
+use crate::cartridge::mapper::{load_ram, ram_offset, write_banked, Mapper};
+use crate::cartridge::rtc::{unix_now, CYCLES_PER_SECOND};
+use crate::cartridge::RtcMode;
+
+const MODE_RAM_READ: u8 = 0x00;
+const MODE_RAM: u8 = 0x0A;
+const MODE_COMMAND: u8 = 0x0B;
+const MODE_RESPONSE: u8 = 0x0C;
+const MODE_SEMAPHORE: u8 = 0x0D;
+const MODE_IR: u8 = 0x0E;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+// Layout of the clock's nibble-addressed memory, as seen through commands.
+const ADDR_MINUTES: u8 = 0x00;
+const ADDR_DAYS: u8 = 0x03;
+const ADDR_ALARM_MINUTES: u8 = 0x58;
+const ADDR_ALARM_DAYS: u8 = 0x5B;
+const ADDR_ALARM_ENABLE: u8 = 0x5F;
+const ADDR_TONE: u8 = 0x26;
+const ADDR_TONE_ENABLE: u8 = 0x27;
+
+const CMD_READ: u8 = 0x1;
+const CMD_WRITE: u8 = 0x2;
+const CMD_WRITE_INC: u8 = 0x3;
+const CMD_ADDR_LOW: u8 = 0x4;
+const CMD_ADDR_HIGH: u8 = 0x5;
+const CMD_EXTENDED: u8 = 0x6;
+
+const EXT_STATUS: u8 = 0x2;
+
+const HUC3_SAVE_SIZE: usize = 17;
+
+/// The HuC3 clock: a minute-of-day and day counter, read and set one nibble
+/// at a time through a small command protocol on the RAM area.
+struct HuC3Clock {
+    mode: RtcMode,
+    minutes: u16,
+    days: u16,
+    alarm_minutes: u16,
+    alarm_days: u16,
+    alarm_enabled: bool,
+    seconds: u32,
+    cycles: u32,
+    synced_at: u64,
+}
+
+impl HuC3Clock {
+    fn new() -> Self {
+        Self {
+            mode: RtcMode::default(),
+            minutes: 0,
+            days: 0,
+            alarm_minutes: 0,
+            alarm_days: 0,
+            alarm_enabled: false,
+            seconds: 0,
+            cycles: 0,
+            synced_at: unix_now(),
+        }
+    }
+
+    fn advance(&mut self, secs: u64) {
+        let secs = secs + self.seconds as u64;
+        self.seconds = (secs % 60) as u32;
+
+        let minutes = secs / 60 + self.minutes as u64;
+        self.minutes = (minutes % MINUTES_PER_DAY as u64) as u16;
+        self.days = ((self.days as u64 + minutes / MINUTES_PER_DAY as u64) & 0xFFF) as u16;
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if self.mode != RtcMode::Emulated {
+            return;
+        }
+
+        self.cycles += cycles;
+        while self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            self.advance(1);
+        }
+    }
+
+    fn sync(&mut self) {
+        if self.mode != RtcMode::WallClock {
+            return;
+        }
+
+        let now = unix_now();
+        self.advance(now.saturating_sub(self.synced_at));
+        self.synced_at = now;
+    }
+
+    fn set_mode(&mut self, mode: RtcMode) {
+        self.sync();
+        self.mode = mode;
+        self.synced_at = unix_now();
+    }
+
+    /// Timestamp, time, and alarm, following the layout other emulators
+    /// append to HuC3 save files.
+    fn save(&self) -> [u8; HUC3_SAVE_SIZE] {
+        let mut buf = [0; HUC3_SAVE_SIZE];
+        let timestamp = match self.mode {
+            RtcMode::Emulated => unix_now(),
+            RtcMode::WallClock => self.synced_at,
+        };
+        LittleEndian::write_u64(&mut buf[0..8], timestamp);
+        LittleEndian::write_u16(&mut buf[8..10], self.minutes);
+        LittleEndian::write_u16(&mut buf[10..12], self.days);
+        LittleEndian::write_u16(&mut buf[12..14], self.alarm_minutes);
+        LittleEndian::write_u16(&mut buf[14..16], self.alarm_days);
+        buf[16] = self.alarm_enabled as u8;
+        buf
+    }
+
+    fn load(&mut self, buf: &[u8]) {
+        if buf.len() < HUC3_SAVE_SIZE {
+            return;
+        }
+
+        self.synced_at = LittleEndian::read_u64(&buf[0..8]);
+        self.minutes = LittleEndian::read_u16(&buf[8..10]) % MINUTES_PER_DAY;
+        self.days = LittleEndian::read_u16(&buf[10..12]) & 0xFFF;
+        self.alarm_minutes = LittleEndian::read_u16(&buf[12..14]);
+        self.alarm_days = LittleEndian::read_u16(&buf[14..16]);
+        self.alarm_enabled = buf[16] & 0x01 != 0;
+        self.seconds = 0;
+        self.cycles = 0;
+        self.sync();
+    }
+}
+
+pub struct HuC3 {
+    ram: Vec<u8>,
+    clock: HuC3Clock,
+    mode: u8,
+    rom_bank: usize,
+    ram_bank: usize,
+    address: u8,
+    response: u8,
+    extended: u8,
+    tone: u8,
+    tone_playing: bool,
+    ir_led: bool,
+    ir_input: bool,
+}
+
+impl HuC3 {
+    pub fn new(ram_size: usize) -> Self {
+        Self {
+            ram: vec![0; ram_size],
+            clock: HuC3Clock::new(),
+            mode: MODE_RAM_READ,
+            rom_bank: 1,
+            ram_bank: 0,
+            address: 0,
+            response: 0,
+            extended: 0,
+            tone: 0,
+            tone_playing: false,
+            ir_led: false,
+            ir_input: false,
+        }
+    }
+
+    fn read_nibble(&self, addr: u8) -> u8 {
+        let nibble = |value: u16, base: u8| (value >> ((addr - base) * 4)) as u8 & 0x0F;
+        match addr {
+            ADDR_MINUTES..ADDR_DAYS => nibble(self.clock.minutes, ADDR_MINUTES),
+            ADDR_DAYS..0x06 => nibble(self.clock.days, ADDR_DAYS),
+            ADDR_TONE => self.tone,
+            ADDR_TONE_ENABLE => self.tone_playing as u8,
+            ADDR_ALARM_MINUTES..ADDR_ALARM_DAYS => nibble(self.clock.alarm_minutes, ADDR_ALARM_MINUTES),
+            ADDR_ALARM_DAYS..ADDR_ALARM_ENABLE => nibble(self.clock.alarm_days, ADDR_ALARM_DAYS),
+            ADDR_ALARM_ENABLE => self.clock.alarm_enabled as u8,
+            _ => 0,
+        }
+    }
+
+    /// Returns whether the nibble belongs to the saved clock state.
+    fn write_nibble(&mut self, addr: u8, value: u8) -> bool {
+        let value = value & 0x0F;
+        let set = |field: &mut u16, base: u8| {
+            let shift = (addr - base) * 4;
+            *field = *field & !(0x0F << shift) | (value as u16) << shift;
+        };
+        match addr {
+            ADDR_MINUTES..ADDR_DAYS => {
+                set(&mut self.clock.minutes, ADDR_MINUTES);
+                self.clock.seconds = 0;
+                self.clock.cycles = 0;
+            }
+            ADDR_DAYS..0x06 => set(&mut self.clock.days, ADDR_DAYS),
+            ADDR_TONE => {
+                self.tone = value;
+                return false;
+            }
+            ADDR_TONE_ENABLE => {
+                self.tone_playing = value & 0x01 != 0;
+                return false;
+            }
+            ADDR_ALARM_MINUTES..ADDR_ALARM_DAYS => set(&mut self.clock.alarm_minutes, ADDR_ALARM_MINUTES),
+            ADDR_ALARM_DAYS..ADDR_ALARM_ENABLE => set(&mut self.clock.alarm_days, ADDR_ALARM_DAYS),
+            ADDR_ALARM_ENABLE => self.clock.alarm_enabled = value & 0x01 != 0,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Runs a command byte: the high nibble selects the command, the low
+    /// nibble is its argument. Returns whether the saved clock state was
+    /// written.
+    fn command(&mut self, byte: u8) -> bool {
+        let arg = byte & 0x0F;
+        match byte >> 4 {
+            CMD_READ => {
+                self.clock.sync();
+                self.response = self.read_nibble(self.address);
+                self.address = self.address.wrapping_add(1);
+            }
+            CMD_WRITE | CMD_WRITE_INC => {
+                self.clock.sync();
+                let saved = self.write_nibble(self.address, arg);
+                if byte >> 4 == CMD_WRITE_INC {
+                    self.address = self.address.wrapping_add(1);
+                }
+                return saved;
+            }
+            CMD_ADDR_LOW => self.address = self.address & 0xF0 | arg,
+            CMD_ADDR_HIGH => self.address = self.address & 0x0F | arg << 4,
+            CMD_EXTENDED => self.extended = arg,
+            _ => {}
+        }
+        false
+    }
+}
+
+impl Mapper for HuC3 {
+    fn rom_banks(&self) -> (usize, usize) {
+        (0, self.rom_bank)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.mode = byte & 0x0F,
+            0x2000..=0x3FFF => self.rom_bank = ((byte & 0x7F) as usize).max(1),
+            0x4000..=0x5FFF => self.ram_bank = (byte & 0x03) as usize,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.mode {
+            MODE_RAM_READ | MODE_RAM => {
+                ram_offset(&self.ram, self.ram_bank, addr).map_or(0xFF, |i| self.ram[i])
+            }
+            MODE_RESPONSE if self.extended == EXT_STATUS => 0x01,
+            MODE_RESPONSE => 0x80 | self.response,
+            MODE_SEMAPHORE => 0x01,
+            MODE_IR => 0xC0 | self.ir_input as u8,
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        match self.mode {
+            MODE_RAM => write_banked(&mut self.ram, self.ram_bank, addr, byte),
+            MODE_COMMAND => self.command(byte),
+            MODE_IR => {
+                self.ir_led = byte & 0x01 != 0;
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn tick(&mut self, cycles: u32) -> bool {
+        self.clock.tick(cycles);
+        false
+    }
+
+    fn set_rtc_mode(&mut self, mode: RtcMode) {
+        self.clock.set_mode(mode);
+    }
+
+    fn ir_led(&self) -> bool {
+        self.ir_led
+    }
+
+    fn set_ir_input(&mut self, light: bool) {
+        self.ir_input = light;
+    }
+
+    fn speaker_tone(&self) -> Option<u8> {
+        self.tone_playing.then_some(self.tone)
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        data.extend_from_slice(&self.clock.save());
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+        self.clock.load(&data[self.ram.len().min(data.len())..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(huc3: &mut HuC3, byte: u8) -> bool {
+        huc3.write_rom(0x0000, MODE_COMMAND);
+        huc3.write_ram(0x0000, byte)
+    }
+
+    fn response(huc3: &mut HuC3) -> u8 {
+        huc3.write_rom(0x0000, MODE_RESPONSE);
+        huc3.read_ram(0x0000)
+    }
+
+    fn set_address(huc3: &mut HuC3, addr: u8) {
+        command(huc3, CMD_ADDR_LOW << 4 | addr & 0x0F);
+        command(huc3, CMD_ADDR_HIGH << 4 | addr >> 4);
+    }
+
+    /// Reads `count` nibbles from `addr` on, the way games do.
+    fn read_nibbles(huc3: &mut HuC3, addr: u8, count: usize) -> Vec<u8> {
+        set_address(huc3, addr);
+        (0..count)
+            .map(|_| {
+                command(huc3, CMD_READ << 4);
+                response(huc3)
+            })
+            .collect()
+    }
+
+    fn write_nibbles(huc3: &mut HuC3, addr: u8, nibbles: &[u8]) {
+        set_address(huc3, addr);
+        for &nibble in nibbles {
+            command(huc3, CMD_WRITE_INC << 4 | nibble);
+        }
+    }
+
+    #[test]
+    fn clock_is_set_and_read_a_nibble_at_a_time() {
+        let mut huc3 = HuC3::new(0x2000);
+        // 21 minutes into day 0x123, low nibbles first.
+        write_nibbles(&mut huc3, ADDR_MINUTES, &[0x5, 0x1, 0x0, 0x3, 0x2, 0x1]);
+        assert_eq!((huc3.clock.minutes, huc3.clock.days), (21, 0x123));
+
+        let nibbles = read_nibbles(&mut huc3, ADDR_MINUTES, 6);
+        assert_eq!(nibbles, [0x85, 0x81, 0x80, 0x83, 0x82, 0x81]);
+    }
+
+    #[test]
+    fn clock_runs_in_minutes_and_days() {
+        let mut huc3 = HuC3::new(0);
+        write_nibbles(&mut huc3, ADDR_MINUTES, &[0xF, 0x9, 0x5]);
+        assert_eq!(huc3.clock.minutes, MINUTES_PER_DAY - 1);
+
+        huc3.tick(60 * CYCLES_PER_SECOND - 1);
+        assert_eq!(huc3.clock.minutes, MINUTES_PER_DAY - 1);
+        huc3.tick(1);
+        assert_eq!((huc3.clock.minutes, huc3.clock.days), (0, 1));
+    }
+
+    #[test]
+    fn status_and_semaphore_read_ready() {
+        let mut huc3 = HuC3::new(0);
+        command(&mut huc3, CMD_EXTENDED << 4 | EXT_STATUS);
+        assert_eq!(response(&mut huc3), 0x01);
+
+        huc3.write_rom(0x0000, MODE_SEMAPHORE);
+        assert_eq!(huc3.read_ram(0x0000), 0x01);
+    }
+
+    #[test]
+    fn only_clock_writes_dirty_the_save() {
+        let mut huc3 = HuC3::new(0);
+        set_address(&mut huc3, ADDR_TONE);
+        assert!(!command(&mut huc3, CMD_WRITE_INC << 4 | 0x3));
+        assert!(!command(&mut huc3, CMD_WRITE << 4 | 0x1));
+        assert_eq!(huc3.speaker_tone(), Some(0x3));
+
+        set_address(&mut huc3, ADDR_ALARM_ENABLE);
+        assert!(command(&mut huc3, CMD_WRITE << 4 | 0x1));
+        assert!(!command(&mut huc3, CMD_READ << 4));
+    }
+
+    #[test]
+    fn footer_round_trips() {
+        let mut huc3 = HuC3::new(0x2000);
+        huc3.write_rom(0x0000, MODE_RAM);
+        huc3.write_ram(0x0042, 0x99);
+        write_nibbles(&mut huc3, ADDR_MINUTES, &[0x4, 0x3, 0x0, 0x2, 0x1, 0x0]);
+        write_nibbles(&mut huc3, ADDR_ALARM_MINUTES, &[0x1, 0x0, 0x0, 0x7, 0x0, 0x0, 0x0, 0x1]);
+
+        let data = huc3.save_data();
+        assert_eq!(data.len(), 0x2000 + HUC3_SAVE_SIZE);
+
+        let mut loaded = HuC3::new(0x2000);
+        loaded.load_save_data(&data);
+        assert_eq!(loaded.read_ram(0x0042), 0x99);
+        assert_eq!((loaded.clock.minutes, loaded.clock.days), (0x34, 0x12));
+        assert_eq!((loaded.clock.alarm_minutes, loaded.clock.alarm_days), (0x1, 0x7));
+        assert!(loaded.clock.alarm_enabled);
+    }
+
+    #[test]
+    fn ir_mode_takes_over_the_ram_window() {
+        let mut huc3 = HuC3::new(0x2000);
+        huc3.write_rom(0x0000, MODE_RAM);
+        huc3.write_ram(0x0000, 0x55);
+
+        huc3.write_rom(0x0000, MODE_IR);
+        assert_eq!(huc3.read_ram(0x0000), 0xC0);
+        huc3.set_ir_input(true);
+        assert_eq!(huc3.read_ram(0x0000), 0xC1);
+        assert!(!huc3.write_ram(0x0000, 0x01));
+        assert!(huc3.ir_led());
+
+        huc3.write_rom(0x0000, MODE_RAM_READ);
+        assert_eq!(huc3.read_ram(0x0000), 0x55);
+    }
+}
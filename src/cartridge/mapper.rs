@@ -0,0 +1,123 @@
+use std::mem::replace;
+use crate::cartridge::{CameraInput, RtcMode, RumbleEvent, TiltInput};
+
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// Bank switching logic of a cartridge. The ROM itself is owned by the
+/// `Cartridge`; mappers only decide which banks are visible and own whatever
+/// sits behind the external RAM area.
+pub trait Mapper {
+    /// ROM banks mapped at 0x0000–0x3FFF and 0x4000–0x7FFF.
+    fn rom_banks(&self) -> (usize, usize) {
+        (0, 1)
+    }
+
+    /// Writes to 0x0000–0x7FFF, which land in the mapper's control registers.
+    fn write_rom(&mut self, addr: u16, byte: u8);
+
+    /// Reads from 0xA000–0xBFFF, `addr` relative to 0xA000.
+    fn read_ram(&self, addr: u16) -> u8;
+
+    /// Writes to 0xA000–0xBFFF, `addr` relative to 0xA000. Returns whether
+    /// battery-backed state changed.
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool;
+
+    /// Entire external RAM, all banks.
+    fn ram(&self) -> &[u8];
+
+    /// Advances mapper-internal clocks by `cycles` T-cycles. Returns whether
+    /// battery-backed state changed, beyond clocks keeping time.
+    fn tick(&mut self, _cycles: u32) -> bool {
+        false
+    }
+
+    fn set_rtc_mode(&mut self, _mode: RtcMode) {}
+
+    /// Whether the rumble motor is currently running.
+    fn rumble(&self) -> bool {
+        false
+    }
+
+    /// Motor state changes since the last call, oldest first. Only the most
+    /// recent ones are kept if nobody calls this.
+    fn take_rumble_events(&mut self) -> Vec<RumbleEvent> {
+        Vec::new()
+    }
+
+    /// Whether the cartridge's infrared LED is lit.
+    fn ir_led(&self) -> bool {
+        false
+    }
+
+    /// Whether the cartridge's infrared sensor currently sees light.
+    fn set_ir_input(&mut self, _light: bool) {}
+
+    /// The tone the cartridge speaker is playing, if any.
+    fn speaker_tone(&self) -> Option<u8> {
+        None
+    }
+
+    /// Where an accelerometer cartridge gets its readings from.
+    fn set_tilt_input(&mut self, _input: Box<dyn TiltInput>) {}
+
+    /// Where a camera cartridge's image sensor gets its frames from.
+    fn set_camera_input(&mut self, _input: Box<dyn CameraInput>) {}
+
+    /// Battery-backed state, in the layout written to save files.
+    fn save_data(&self) -> Vec<u8> {
+        self.ram().to_vec()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]);
+}
+
+pub struct RomOnly {
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(ram_size: usize) -> Self {
+        Self { ram: vec![0; ram_size] }
+    }
+}
+
+impl Mapper for RomOnly {
+    fn write_rom(&mut self, _addr: u16, _byte: u8) {}
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        self.ram.get(addr as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        self.ram.get_mut(addr as usize).is_some_and(|b| replace(b, byte) != byte)
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+/// Copies as much of `data` as fits into `ram`.
+pub fn load_ram(ram: &mut [u8], data: &[u8]) {
+    let len = ram.len().min(data.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
+
+/// Writes `byte` to banked RAM, returning whether that changed anything.
+pub fn write_banked(ram: &mut [u8], bank: usize, addr: u16, byte: u8) -> bool {
+    ram_offset(ram, bank, addr).is_some_and(|i| replace(&mut ram[i], byte) != byte)
+}
+
+/// Offset of `addr` (relative to 0xA000) in banked RAM, wrapping around the
+/// number of banks actually present.
+pub fn ram_offset(ram: &[u8], bank: usize, addr: u16) -> Option<usize> {
+    if ram.is_empty() {
+        return None;
+    }
+
+    Some((bank * RAM_BANK_SIZE + addr as usize) % ram.len())
+}
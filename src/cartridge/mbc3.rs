@@ -0,0 +1,148 @@
+use crate::cartridge::mapper::{load_ram, ram_offset, write_banked, Mapper};
+use crate::cartridge::rtc::{Rtc, RTC_SAVE_SIZE};
+use crate::cartridge::RtcMode;
+
+pub struct Mbc3 {
+    ram: Vec<u8>,
+    rtc: Option<Rtc>,
+    ram_enabled: bool,
+    rom_bank: usize,
+    ram_select: u8,
+}
+
+impl Mbc3 {
+    pub fn new(ram_size: usize, has_rtc: bool) -> Self {
+        Self {
+            ram: vec![0; ram_size],
+            rtc: has_rtc.then(Rtc::new),
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_select: 0,
+        }
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn rom_banks(&self) -> (usize, usize) {
+        (0, self.rom_bank)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = ((byte & 0x7F) as usize).max(1),
+            0x4000..=0x5FFF => self.ram_select = byte,
+            _ => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.latch(byte);
+                }
+            }
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        match (self.ram_select, &self.rtc) {
+            (0x00..=0x07, _) => ram_offset(&self.ram, self.ram_select as usize, addr)
+                .map_or(0xFF, |i| self.ram[i]),
+            (0x08..=0x0C, Some(rtc)) => rtc.read(self.ram_select),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        if !self.ram_enabled {
+            return false;
+        }
+
+        match (self.ram_select, &mut self.rtc) {
+            (0x00..=0x07, _) => write_banked(&mut self.ram, self.ram_select as usize, addr, byte),
+            (0x08..=0x0C, Some(rtc)) => rtc.write(self.ram_select, byte),
+            _ => false,
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn tick(&mut self, cycles: u32) -> bool {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.tick(cycles);
+        }
+        false
+    }
+
+    fn set_rtc_mode(&mut self, mode: RtcMode) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.set_mode(mode);
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = &self.rtc {
+            data.extend_from_slice(&rtc.save());
+        }
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+        if let Some(rtc) = &mut self.rtc {
+            let footer = &data[self.ram.len().min(data.len())..];
+            if footer.len() <= RTC_SAVE_SIZE {
+                rtc.load(footer);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changes_dirty_the_save() {
+        let mut mbc = Mbc3::new(0x2000, true);
+        assert!(!mbc.write_ram(0x0000, 0x12), "RAM disabled");
+
+        mbc.write_rom(0x0000, 0x0A);
+        assert!(mbc.write_ram(0x0000, 0x12));
+        assert!(!mbc.write_ram(0x0000, 0x12));
+
+        mbc.write_rom(0x4000, 0x09);
+        assert!(mbc.write_ram(0x0000, 0x20));
+        assert!(!mbc.write_ram(0x0000, 0x20));
+
+        // Latching only copies registers for reading.
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+        assert!(!mbc.write_ram(0x0000, 0x20));
+        assert_eq!(mbc.read_ram(0x0000), 0x20);
+    }
+
+    #[test]
+    fn save_data_is_ram_then_48_byte_footer() {
+        let mut mbc = Mbc3::new(0x2000, true);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(0x0010, 0x77);
+        mbc.write_rom(0x4000, 0x0A);
+        mbc.write_ram(0x0000, 5);
+
+        let data = mbc.save_data();
+        assert_eq!(data.len(), 0x2000 + RTC_SAVE_SIZE);
+
+        let mut loaded = Mbc3::new(0x2000, true);
+        loaded.load_save_data(&data);
+        loaded.write_rom(0x0000, 0x0A);
+        assert_eq!(loaded.read_ram(0x0010), 0x77);
+        loaded.write_rom(0x6000, 0x00);
+        loaded.write_rom(0x6000, 0x01);
+        loaded.write_rom(0x4000, 0x0A);
+        assert_eq!(loaded.read_ram(0x0000), 5);
+    }
+}
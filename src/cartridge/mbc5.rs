@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use crate::cartridge::mapper::{load_ram, ram_offset, write_banked, Mapper};
+
+const RUMBLE_MOTOR: u8 = 0x08;
+/// How many motor changes are kept for a frontend that isn't draining them.
+/// Games pulse the motor many times a frame, so older ones are dropped.
+const MAX_RUMBLE_EVENTS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RumbleEvent {
+    On,
+    Off,
+}
+
+pub struct Mbc5 {
+    ram: Vec<u8>,
+    has_rumble: bool,
+    ram_enabled: bool,
+    rom_bank: usize,
+    ram_bank: usize,
+    rumble: bool,
+    rumble_events: VecDeque<RumbleEvent>,
+}
+
+impl Mbc5 {
+    pub fn new(ram_size: usize, has_rumble: bool) -> Self {
+        Self {
+            ram: vec![0; ram_size],
+            has_rumble,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble: false,
+            rumble_events: VecDeque::new(),
+        }
+    }
+
+    fn set_rumble(&mut self, on: bool) {
+        if on != self.rumble {
+            self.rumble = on;
+            if self.rumble_events.len() == MAX_RUMBLE_EVENTS {
+                self.rumble_events.pop_front();
+            }
+            self.rumble_events.push_back(if on { RumbleEvent::On } else { RumbleEvent::Off });
+        }
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn rom_banks(&self) -> (usize, usize) {
+        (0, self.rom_bank)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = self.rom_bank & 0x100 | byte as usize,
+            0x3000..=0x3FFF => self.rom_bank = self.rom_bank & 0xFF | (byte as usize & 0x01) << 8,
+            0x4000..=0x5FFF => {
+                if self.has_rumble {
+                    self.set_rumble(byte & RUMBLE_MOTOR != 0);
+                    self.ram_bank = (byte & 0x07) as usize;
+                } else {
+                    self.ram_bank = (byte & 0x0F) as usize;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        ram_offset(&self.ram, self.ram_bank, addr).map_or(0xFF, |i| self.ram[i])
+    }
+
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        self.ram_enabled && write_banked(&mut self.ram, self.ram_bank, addr, byte)
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+
+    fn take_rumble_events(&mut self) -> Vec<RumbleEvent> {
+        self.rumble_events.drain(..).collect()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rumble_events_are_capped() {
+        let mut mbc = Mbc5::new(0, true);
+        for _ in 0..MAX_RUMBLE_EVENTS {
+            mbc.write_rom(0x4000, RUMBLE_MOTOR);
+            mbc.write_rom(0x4000, 0x00);
+        }
+
+        let events = mbc.take_rumble_events();
+        assert_eq!(events.len(), MAX_RUMBLE_EVENTS);
+        assert_eq!(events.last(), Some(&RumbleEvent::Off));
+        assert!(mbc.take_rumble_events().is_empty());
+    }
+}
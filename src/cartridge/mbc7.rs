@@ -0,0 +1,373 @@
+use crate::cartridge::mapper::{load_ram, Mapper};
+
+const EEPROM_SIZE: usize = 256;
+
+const ACCEL_CENTER: f32 = 0x81D0 as f32;
+const ACCEL_PER_G: f32 = 0x70 as f32;
+const ACCEL_ERASED: u16 = 0x8000;
+
+const EEPROM_DO: u8 = 0x01;
+const EEPROM_DI: u8 = 0x02;
+const EEPROM_CLK: u8 = 0x40;
+const EEPROM_CS: u8 = 0x80;
+
+/// Source of the accelerometer readings, in g along the screen's X and Y
+/// axes. Closures returning `(x, y)` work too, which is handy for scripting
+/// tilt sequences in tests.
+pub trait TiltInput {
+    fn tilt(&mut self) -> (f32, f32);
+}
+
+impl<F: FnMut() -> (f32, f32)> TiltInput for F {
+    fn tilt(&mut self) -> (f32, f32) {
+        self()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EepromState {
+    Idle,
+    Command,
+    Reading { word: u16, bits: u8 },
+    Writing { addr: Option<u8> },
+}
+
+/// A 93LC56 serial EEPROM in 16-bit organisation, driven one pin at a time.
+struct Eeprom {
+    data: [u8; EEPROM_SIZE],
+    state: EepromState,
+    cs: bool,
+    clk: bool,
+    di: bool,
+    dout: bool,
+    write_enabled: bool,
+    shift: u16,
+    bits: u8,
+}
+
+impl Eeprom {
+    fn new() -> Self {
+        Self {
+            data: [0xFF; EEPROM_SIZE],
+            state: EepromState::Idle,
+            cs: false,
+            clk: false,
+            di: false,
+            dout: true,
+            write_enabled: false,
+            shift: 0,
+            bits: 0,
+        }
+    }
+
+    fn pins(&self) -> u8 {
+        (self.cs as u8) << 7 | (self.clk as u8) << 6 | (self.di as u8) << 1 | self.dout as u8
+    }
+
+    fn word(&self, addr: u8) -> u16 {
+        let i = (addr as usize & 0x7F) * 2;
+        u16::from_le_bytes([self.data[i], self.data[i + 1]])
+    }
+
+    fn set_word(&mut self, addr: u8, word: u16) {
+        let i = (addr as usize & 0x7F) * 2;
+        self.data[i..i + 2].copy_from_slice(&word.to_le_bytes());
+    }
+
+    fn write_pins(&mut self, byte: u8) {
+        let cs = byte & EEPROM_CS != 0;
+        let clk = byte & EEPROM_CLK != 0;
+        self.di = byte & EEPROM_DI != 0;
+
+        if !cs {
+            // Deselecting aborts whatever was in progress, writes included.
+            self.state = EepromState::Idle;
+            self.cs = false;
+            self.clk = clk;
+            return;
+        }
+
+        let rising = clk && !self.clk;
+        self.cs = true;
+        self.clk = clk;
+        if rising {
+            self.clock_in();
+        }
+    }
+
+    fn clock_in(&mut self) {
+        match self.state {
+            EepromState::Idle => {
+                if self.di {
+                    self.state = EepromState::Command;
+                    self.shift = 0;
+                    self.bits = 0;
+                }
+            }
+            EepromState::Command => {
+                self.shift_in();
+                if self.bits == 10 {
+                    self.decode();
+                }
+            }
+            EepromState::Reading { word, bits } => {
+                self.dout = word & 0x8000 != 0;
+                self.state = match bits {
+                    1 => EepromState::Idle,
+                    _ => EepromState::Reading { word: word << 1, bits: bits - 1 },
+                };
+            }
+            EepromState::Writing { addr } => {
+                self.shift_in();
+                if self.bits == 16 {
+                    if self.write_enabled {
+                        match addr {
+                            Some(addr) => self.set_word(addr, self.shift),
+                            None => (0..0x80).for_each(|a| self.set_word(a, self.shift)),
+                        }
+                    }
+                    self.dout = true;
+                    self.state = EepromState::Idle;
+                }
+            }
+        }
+    }
+
+    fn shift_in(&mut self) {
+        self.shift = self.shift << 1 | self.di as u16;
+        self.bits += 1;
+    }
+
+    /// Two opcode bits followed by eight address bits.
+    fn decode(&mut self) {
+        let addr = self.shift as u8 & 0x7F;
+        self.state = EepromState::Idle;
+        match (self.shift >> 8) & 0x03 {
+            0b10 => {
+                self.dout = false;
+                self.state = EepromState::Reading { word: self.word(addr), bits: 16 };
+            }
+            0b01 => self.start_write(Some(addr)),
+            0b11 => {
+                if self.write_enabled {
+                    self.set_word(addr, 0xFFFF);
+                }
+                self.dout = true;
+            }
+            _ => match (self.shift >> 6) & 0x03 {
+                0b11 => self.write_enabled = true,
+                0b00 => self.write_enabled = false,
+                0b10 => {
+                    if self.write_enabled {
+                        self.data = [0xFF; EEPROM_SIZE];
+                    }
+                    self.dout = true;
+                }
+                _ => self.start_write(None),
+            },
+        }
+    }
+
+    fn start_write(&mut self, addr: Option<u8>) {
+        self.state = EepromState::Writing { addr };
+        self.shift = 0;
+        self.bits = 0;
+    }
+}
+
+pub struct Mbc7 {
+    eeprom: Eeprom,
+    tilt: Option<Box<dyn TiltInput>>,
+    ram_enabled: [bool; 2],
+    rom_bank: usize,
+    accel_x: u16,
+    accel_y: u16,
+}
+
+impl Mbc7 {
+    pub fn new() -> Self {
+        Self {
+            eeprom: Eeprom::new(),
+            tilt: None,
+            ram_enabled: [false; 2],
+            rom_bank: 1,
+            accel_x: ACCEL_ERASED,
+            accel_y: ACCEL_ERASED,
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.ram_enabled == [true, true]
+    }
+
+    fn latch_accelerometer(&mut self) {
+        let (x, y) = self.tilt.as_mut().map_or((0.0, 0.0), |input| input.tilt());
+        let to_reading = |g: f32| (ACCEL_CENTER + g * ACCEL_PER_G).clamp(0.0, u16::MAX as f32) as u16;
+        self.accel_x = to_reading(-x);
+        self.accel_y = to_reading(y);
+    }
+}
+
+impl Mapper for Mbc7 {
+    fn rom_banks(&self) -> (usize, usize) {
+        (0, self.rom_bank)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled[0] = byte == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (byte & 0x7F) as usize,
+            0x4000..=0x5FFF => self.ram_enabled[1] = byte == 0x40,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.enabled() || addr >= 0x1000 {
+            return 0xFF;
+        }
+
+        match addr >> 4 & 0x0F {
+            0x2 => self.accel_x as u8,
+            0x3 => (self.accel_x >> 8) as u8,
+            0x4 => self.accel_y as u8,
+            0x5 => (self.accel_y >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.pins(),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        if !self.enabled() || addr >= 0x1000 {
+            return false;
+        }
+
+        match addr >> 4 & 0x0F {
+            0x0 if byte == 0x55 => {
+                self.accel_x = ACCEL_ERASED;
+                self.accel_y = ACCEL_ERASED;
+            }
+            0x1 if byte == 0xAA && self.accel_x == ACCEL_ERASED => self.latch_accelerometer(),
+            0x8 => {
+                let before = self.eeprom.data;
+                self.eeprom.write_pins(byte);
+                return self.eeprom.data != before;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.eeprom.data
+    }
+
+    fn set_tilt_input(&mut self, input: Box<dyn TiltInput>) {
+        self.tilt = Some(input);
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.eeprom.data, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> Mbc7 {
+        let mut mbc = Mbc7::new();
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x40);
+        mbc
+    }
+
+    fn clock(mbc: &mut Mbc7, di: bool) {
+        let di = if di { EEPROM_DI } else { 0 };
+        mbc.write_ram(0x80, EEPROM_CS | di);
+        mbc.write_ram(0x80, EEPROM_CS | EEPROM_CLK | di);
+    }
+
+    fn send(mbc: &mut Mbc7, bits: u16, count: u8) {
+        for i in (0..count).rev() {
+            clock(mbc, bits >> i & 1 != 0);
+        }
+    }
+
+    /// Start bit, two opcode bits and eight address bits.
+    fn command(mbc: &mut Mbc7, opcode: u16, addr: u8) {
+        send(mbc, 1 << 10 | opcode << 8 | addr as u16, 11);
+    }
+
+    fn deselect(mbc: &mut Mbc7) {
+        mbc.write_ram(0x80, 0x00);
+    }
+
+    fn read_word(mbc: &mut Mbc7, addr: u8) -> u16 {
+        command(mbc, 0b10, addr);
+        let mut word = 0;
+        for _ in 0..16 {
+            clock(mbc, false);
+            word = word << 1 | (mbc.read_ram(0x80) & EEPROM_DO) as u16;
+        }
+        deselect(mbc);
+        word
+    }
+
+    #[test]
+    fn eeprom_write_then_read() {
+        let mut mbc = enabled();
+        command(&mut mbc, 0b00, 0xC0);
+        deselect(&mut mbc);
+        command(&mut mbc, 0b01, 0x05);
+        send(&mut mbc, 0x1234, 16);
+        deselect(&mut mbc);
+
+        assert_eq!(read_word(&mut mbc, 0x05), 0x1234);
+    }
+
+    #[test]
+    fn deselect_aborts_write() {
+        let mut mbc = enabled();
+        command(&mut mbc, 0b00, 0xC0);
+        deselect(&mut mbc);
+        command(&mut mbc, 0b01, 0x00);
+        send(&mut mbc, 0x12, 8);
+        deselect(&mut mbc);
+
+        // The next command's bits mustn't be taken as the rest of the data.
+        assert_eq!(read_word(&mut mbc, 0x00), 0xFFFF);
+        assert_eq!(read_word(&mut mbc, 0x00), 0xFFFF);
+    }
+
+    fn accelerometer(mbc: &mut Mbc7) -> (u16, u16) {
+        mbc.write_ram(0x00, 0x55);
+        mbc.write_ram(0x10, 0xAA);
+        let x = u16::from_le_bytes([mbc.read_ram(0x20), mbc.read_ram(0x30)]);
+        let y = u16::from_le_bytes([mbc.read_ram(0x40), mbc.read_ram(0x50)]);
+        (x, y)
+    }
+
+    #[test]
+    fn tilt_sequence_reaches_accelerometer() {
+        let mut mbc = enabled();
+        let mut tilts = [(0.0, 0.0), (1.0, -0.5), (-2.0, 1.0)].into_iter();
+        mbc.set_tilt_input(Box::new(move || tilts.next().unwrap_or((0.0, 0.0))));
+
+        assert_eq!(accelerometer(&mut mbc), (0x81D0, 0x81D0));
+        assert_eq!(accelerometer(&mut mbc), (0x81D0 - 0x70, 0x81D0 - 0x38));
+        assert_eq!(accelerometer(&mut mbc), (0x81D0 + 0xE0, 0x81D0 + 0x70));
+    }
+
+    #[test]
+    fn latch_needs_erase_first() {
+        let mut mbc = enabled();
+        let mut tilts = [(1.0, 1.0), (-1.0, -1.0)].into_iter();
+        mbc.set_tilt_input(Box::new(move || tilts.next().unwrap_or((0.0, 0.0))));
+
+        let first = accelerometer(&mut mbc);
+        mbc.write_ram(0x10, 0xAA);
+        assert_eq!(u16::from_le_bytes([mbc.read_ram(0x20), mbc.read_ram(0x30)]), first.0);
+    }
+}
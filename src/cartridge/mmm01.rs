@@ -0,0 +1,153 @@
+use crate::cartridge::mapper::{load_ram, ram_offset, write_banked, Mapper};
+
+const MAP_ENABLE: u8 = 0x40;
+
+/// Multi-game compilation mapper. Boots into the menu in the last 32 KiB of
+/// ROM; the menu picks a game by setting the outer bank bits and then locks
+/// them, after which the cartridge behaves much like an MBC1 confined to
+/// that game.
+pub struct Mmm01 {
+    ram: Vec<u8>,
+    rom_bank_count: usize,
+    mapped: bool,
+    ram_enabled: bool,
+    rom_bank: usize,
+    rom_mask: usize,
+    ram_bank: usize,
+}
+
+impl Mmm01 {
+    pub fn new(rom_bank_count: usize, ram_size: usize) -> Self {
+        Self {
+            ram: vec![0; ram_size],
+            rom_bank_count,
+            mapped: false,
+            ram_enabled: false,
+            rom_bank: 0,
+            rom_mask: 0,
+            ram_bank: 0,
+        }
+    }
+
+    /// Bits of the ROM bank number the game itself may still change.
+    fn selectable(&self) -> usize {
+        0x1F & !self.rom_mask
+    }
+}
+
+impl Mapper for Mmm01 {
+    fn rom_banks(&self) -> (usize, usize) {
+        if !self.mapped {
+            return (self.rom_bank_count - 2, self.rom_bank_count - 1);
+        }
+
+        let base = self.rom_bank & !self.selectable();
+        let bank = (self.rom_bank & self.selectable()).max(1);
+        (base, base | bank)
+    }
+
+    fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => {
+                self.ram_enabled = byte & 0x0F == 0x0A;
+                if byte & MAP_ENABLE != 0 {
+                    self.mapped = true;
+                }
+            }
+            0x2000..=0x3FFF => {
+                let writable = if self.mapped { self.selectable() } else { 0x7F };
+                self.rom_bank = self.rom_bank & !writable | byte as usize & writable;
+            }
+            0x4000..=0x5FFF => {
+                let writable = if self.mapped { 0x03 } else { 0x0F };
+                self.ram_bank = self.ram_bank & !writable | byte as usize & writable;
+                if !self.mapped {
+                    self.rom_bank = self.rom_bank & 0x7F | (byte as usize >> 4 & 0x03) << 7;
+                }
+            }
+            _ => {
+                if !self.mapped {
+                    self.rom_mask = (byte as usize >> 2 & 0x0F) << 1;
+                }
+            }
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        ram_offset(&self.ram, self.ram_bank, addr).map_or(0xFF, |i| self.ram[i])
+    }
+
+    fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        self.ram_enabled && write_banked(&mut self.ram, self.ram_bank, addr, byte)
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Maps the four-bank game starting at bank 0x10 of a 64-bank cart.
+    fn select_game(mmm01: &mut Mmm01) {
+        mmm01.write_rom(0x2000, 0x10);
+        mmm01.write_rom(0x6000, 0x38);
+        mmm01.write_rom(0x0000, MAP_ENABLE);
+    }
+
+    #[test]
+    fn boots_into_the_menu() {
+        let mut mmm01 = Mmm01::new(64, 0);
+        assert_eq!(mmm01.rom_banks(), (62, 63));
+
+        mmm01.write_rom(0x2000, 0x10);
+        assert_eq!(mmm01.rom_banks(), (62, 63));
+    }
+
+    #[test]
+    fn game_is_confined_to_its_banks() {
+        let mut mmm01 = Mmm01::new(64, 0);
+        select_game(&mut mmm01);
+        assert_eq!(mmm01.rom_banks(), (0x10, 0x11));
+
+        mmm01.write_rom(0x2000, 0x03);
+        assert_eq!(mmm01.rom_banks(), (0x10, 0x13));
+        mmm01.write_rom(0x2000, 0x1C);
+        assert_eq!(mmm01.rom_banks(), (0x10, 0x11));
+
+        // The menu's registers are locked once mapped.
+        mmm01.write_rom(0x6000, 0x00);
+        mmm01.write_rom(0x4000, 0x30);
+        mmm01.write_rom(0x0000, 0x00);
+        mmm01.write_rom(0x2000, 0x1F);
+        assert_eq!(mmm01.rom_banks(), (0x10, 0x13));
+    }
+
+    #[test]
+    fn ram_is_banked_once_enabled() {
+        let mut mmm01 = Mmm01::new(64, 0x8000);
+        select_game(&mut mmm01);
+        assert!(!mmm01.write_ram(0x0000, 0x11));
+        assert_eq!(mmm01.read_ram(0x0000), 0xFF);
+
+        mmm01.write_rom(0x0000, 0x0A);
+        assert!(mmm01.write_ram(0x0000, 0x11));
+        mmm01.write_rom(0x4000, 0x03);
+        assert!(mmm01.write_ram(0x0000, 0x33));
+
+        assert_eq!(mmm01.read_ram(0x0000), 0x33);
+        mmm01.write_rom(0x4000, 0x00);
+        assert_eq!(mmm01.read_ram(0x0000), 0x11);
+        assert_eq!(mmm01.ram()[3 * 0x2000], 0x33);
+    }
+}
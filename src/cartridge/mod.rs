@@ -0,0 +1,20 @@
+mod bootleg;
+mod camera;
+mod cartridge;
+mod huc1;
+mod huc3;
+mod mapper;
+mod mbc3;
+mod mbc5;
+mod mbc7;
+mod mmm01;
+mod rtc;
+mod save;
+mod sensor;
+
+pub use cartridge::{overrides_path, read_overrides, rom_crc32, Cartridge, CartridgeError, MapperKind, MapperOverrides};
+pub use mbc5::RumbleEvent;
+pub use mbc7::TiltInput;
+pub use rtc::RtcMode;
+pub use save::save_path;
+pub use sensor::{CameraInput, FrameDirectory, StillImage};
@@ -0,0 +1,372 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{ByteOrder, LittleEndian};
+
+pub const CYCLES_PER_SECOND: u32 = (crate::MASTER_CLOCK / 2) as u32;
+
+pub const RTC_SAVE_SIZE: usize = 48;
+
+const DH_DAY_MSB: u8 = 0x01;
+const DH_HALT: u8 = 0x40;
+const DH_CARRY: u8 = 0x80;
+
+/// What drives the cartridge clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RtcMode {
+    /// Advances with emulated cycles, so time stands still while the
+    /// emulator is paused or closed.
+    #[default]
+    Emulated,
+    /// Follows the host's wall clock, including time passed between sessions.
+    WallClock,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct RtcRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    day_low: u8,
+    day_high: u8,
+}
+
+impl RtcRegisters {
+    fn read(&self, reg: u8) -> u8 {
+        match reg {
+            0x08 => self.seconds & 0x3F,
+            0x09 => self.minutes & 0x3F,
+            0x0A => self.hours & 0x1F,
+            0x0B => self.day_low,
+            0x0C => self.day_high & (DH_DAY_MSB | DH_HALT | DH_CARRY),
+            _ => 0xFF,
+        }
+    }
+
+    fn days(&self) -> u16 {
+        (self.day_high as u16 & DH_DAY_MSB as u16) << 8 | self.day_low as u16
+    }
+
+    fn set_days(&mut self, days: u16) {
+        self.day_low = days as u8;
+        self.day_high = self.day_high & !DH_DAY_MSB | (days >> 8) as u8 & DH_DAY_MSB;
+    }
+
+    fn in_range(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    /// Counts one second. Out-of-range values keep counting up to the
+    /// register width and then wrap to zero without carrying.
+    fn step(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+
+        self.add_days(1);
+    }
+
+    fn add_days(&mut self, days: u64) {
+        let days = self.days() as u64 + days;
+        if days > 0x1FF {
+            self.day_high |= DH_CARRY;
+        }
+        self.set_days((days & 0x1FF) as u16);
+    }
+
+    fn advance(&mut self, mut secs: u64) {
+        while secs > 0 && !self.in_range() {
+            self.step();
+            secs -= 1;
+        }
+
+        let total = secs
+            + self.seconds as u64
+            + self.minutes as u64 * 60
+            + self.hours as u64 * 3600;
+
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+
+        let days = total / 86400;
+        if days > 0 {
+            self.add_days(days);
+        }
+    }
+
+    fn save(&self, buf: &mut [u8]) {
+        let regs = [self.seconds, self.minutes, self.hours, self.day_low, self.day_high];
+        for (chunk, reg) in buf.chunks_exact_mut(4).zip(regs) {
+            LittleEndian::write_u32(chunk, reg as u32);
+        }
+    }
+
+    fn load(buf: &[u8]) -> Self {
+        let reg = |n: usize| LittleEndian::read_u32(&buf[n*4..n*4+4]) as u8;
+        Self {
+            seconds: reg(0),
+            minutes: reg(1),
+            hours: reg(2),
+            day_low: reg(3),
+            day_high: reg(4),
+        }
+    }
+}
+
+/// The MBC3 real-time clock.
+pub struct Rtc {
+    mode: RtcMode,
+    live: RtcRegisters,
+    latched: RtcRegisters,
+    latch_armed: bool,
+    cycles: u32,
+    synced_at: u64,
+}
+
+impl Rtc {
+    pub fn new() -> Self {
+        Self {
+            mode: RtcMode::default(),
+            live: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            latch_armed: false,
+            cycles: 0,
+            synced_at: unix_now(),
+        }
+    }
+
+    /// Switches the clock source. Set this before loading a save so that the
+    /// time passed since it was written is applied in wall-clock mode.
+    pub fn set_mode(&mut self, mode: RtcMode) {
+        self.sync();
+        self.mode = mode;
+        self.synced_at = unix_now();
+    }
+
+    fn halted(&self) -> bool {
+        self.live.day_high & DH_HALT != 0
+    }
+
+    /// Reads the latched copy of register `reg` (0x08–0x0C).
+    pub fn read(&self, reg: u8) -> u8 {
+        self.latched.read(reg)
+    }
+
+    /// Writes live register `reg`, returning whether its value changed.
+    pub fn write(&mut self, reg: u8, byte: u8) -> bool {
+        self.sync();
+        let before = self.live;
+        match reg {
+            0x08 => {
+                self.live.seconds = byte & 0x3F;
+                self.cycles = 0;
+            }
+            0x09 => self.live.minutes = byte & 0x3F,
+            0x0A => self.live.hours = byte & 0x1F,
+            0x0B => self.live.day_low = byte,
+            0x0C => self.live.day_high = byte & (DH_DAY_MSB | DH_HALT | DH_CARRY),
+            _ => {}
+        }
+        self.live != before
+    }
+
+    /// Writes to 0x6000–0x7FFF. Writing 0x00 followed by 0x01 copies the
+    /// live registers into the latched ones.
+    pub fn latch(&mut self, byte: u8) {
+        if self.latch_armed && byte == 0x01 {
+            self.sync();
+            self.latched = self.live;
+        }
+        self.latch_armed = byte == 0x00;
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        if self.mode != RtcMode::Emulated || self.halted() {
+            return;
+        }
+
+        self.cycles += cycles;
+        while self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            self.live.step();
+        }
+    }
+
+    /// Catches up with the host clock in wall-clock mode.
+    fn sync(&mut self) {
+        if self.mode != RtcMode::WallClock {
+            return;
+        }
+
+        let now = unix_now();
+        if !self.halted() {
+            self.live.advance(now.saturating_sub(self.synced_at));
+        }
+        self.synced_at = now;
+    }
+
+    /// The 48-byte footer most emulators append to MBC3 save files: live and
+    /// latched registers as little-endian u32s, then a 64-bit UNIX timestamp.
+    pub fn save(&self) -> [u8; RTC_SAVE_SIZE] {
+        let mut buf = [0; RTC_SAVE_SIZE];
+        self.live.save(&mut buf[0..20]);
+        self.latched.save(&mut buf[20..40]);
+
+        let timestamp = match self.mode {
+            RtcMode::Emulated => unix_now(),
+            RtcMode::WallClock => self.synced_at,
+        };
+        LittleEndian::write_u64(&mut buf[40..48], timestamp);
+
+        buf
+    }
+
+    pub fn load(&mut self, buf: &[u8]) {
+        if buf.len() < 44 {
+            return;
+        }
+
+        self.live = RtcRegisters::load(&buf[0..20]);
+        self.latched = RtcRegisters::load(&buf[20..40]);
+        self.cycles = 0;
+        self.synced_at = match buf.len() {
+            44..=47 => LittleEndian::read_u32(&buf[40..44]) as u64,
+            _ => LittleEndian::read_u64(&buf[40..48]),
+        };
+        self.sync();
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An emulated-time clock with the live registers set to `regs`, in the
+    /// order seconds, minutes, hours, day low, day high.
+    fn clock(regs: [u8; 5]) -> Rtc {
+        let mut rtc = Rtc::new();
+        for (reg, byte) in (0x08..).zip(regs) {
+            rtc.write(reg, byte);
+        }
+        rtc
+    }
+
+    fn latched(rtc: &mut Rtc) -> [u8; 5] {
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        [0x08, 0x09, 0x0A, 0x0B, 0x0C].map(|reg| rtc.read(reg))
+    }
+
+    #[test]
+    fn seconds_roll_into_minutes_hours_and_days() {
+        let mut rtc = clock([59, 59, 23, 0x41, 0]);
+        rtc.tick(CYCLES_PER_SECOND - 1);
+        assert_eq!(latched(&mut rtc), [59, 59, 23, 0x41, 0]);
+        rtc.tick(1);
+        assert_eq!(latched(&mut rtc), [0, 0, 0, 0x42, 0]);
+
+        let mut rtc = clock([59, 59, 23, 0xFF, 0]);
+        rtc.tick(CYCLES_PER_SECOND);
+        assert_eq!(latched(&mut rtc), [0, 0, 0, 0x00, DH_DAY_MSB]);
+    }
+
+    #[test]
+    fn day_counter_overflow_sets_carry() {
+        let mut rtc = clock([59, 59, 23, 0xFF, DH_DAY_MSB]);
+        rtc.tick(CYCLES_PER_SECOND);
+        assert_eq!(latched(&mut rtc), [0, 0, 0, 0x00, DH_CARRY]);
+
+        // The carry stays set until written back to zero.
+        rtc.tick(CYCLES_PER_SECOND);
+        assert_eq!(latched(&mut rtc)[4], DH_CARRY);
+        rtc.write(0x0C, 0);
+        assert_eq!(latched(&mut rtc)[4], 0);
+    }
+
+    #[test]
+    fn halt_freezes_time() {
+        let mut rtc = clock([10, 0, 0, 0, DH_HALT]);
+        rtc.tick(5 * CYCLES_PER_SECOND);
+        assert_eq!(latched(&mut rtc)[0], 10);
+
+        rtc.write(0x0C, 0);
+        rtc.tick(5 * CYCLES_PER_SECOND);
+        assert_eq!(latched(&mut rtc)[0], 15);
+    }
+
+    #[test]
+    fn latch_needs_0_then_1() {
+        let mut rtc = clock([30, 0, 0, 0, 0]);
+        assert_eq!(rtc.read(0x08), 0);
+
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 0, "1 without 0 first");
+        rtc.latch(0x00);
+        rtc.latch(0x02);
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 0, "0 not directly before 1");
+
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 30);
+
+        // The latched copy holds still while the clock runs on.
+        rtc.tick(CYCLES_PER_SECOND);
+        assert_eq!(rtc.read(0x08), 30);
+    }
+
+    #[test]
+    fn footer_round_trips() {
+        let mut rtc = clock([1, 2, 3, 4, DH_DAY_MSB | DH_HALT]);
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        rtc.write(0x08, 5);
+
+        let footer = rtc.save();
+        let mut loaded = Rtc::new();
+        loaded.load(&footer);
+        assert_eq!(loaded.live, rtc.live);
+        assert_eq!(loaded.latched, rtc.latched);
+        assert_eq!(loaded.synced_at, LittleEndian::read_u64(&footer[40..48]));
+    }
+
+    #[test]
+    fn legacy_44_byte_footer_loads() {
+        let rtc = clock([1, 2, 3, 4, 0]);
+        let mut footer = rtc.save();
+        LittleEndian::write_u32(&mut footer[40..44], 1_600_000_000);
+
+        let mut loaded = Rtc::new();
+        loaded.load(&footer[..44]);
+        assert_eq!(loaded.live, rtc.live);
+        assert_eq!(loaded.synced_at, 1_600_000_000);
+    }
+
+    #[test]
+    fn writes_report_changes() {
+        let mut rtc = clock([1, 0, 0, 0, 0]);
+        assert!(!rtc.write(0x08, 1));
+        assert!(rtc.write(0x08, 2));
+        assert!(!rtc.write(0x0D, 2));
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The `.sav` file holding a cartridge's battery-backed state: raw external
+/// RAM followed by any clock footer, as other emulators lay it out.
+pub struct SaveFile {
+    path: PathBuf,
+    dirty: bool,
+}
+
+impl SaveFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, dirty: false }
+    }
+
+    /// Contents of the file, or `None` if there is no save yet.
+    pub fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Replaces the file with `data` by writing a temporary file next to it
+    /// and renaming it over the old one, so a crash mid-write leaves the
+    /// previous save intact.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// Where the save file for the ROM at `rom_path` lives.
+pub fn save_path(rom_path: &Path) -> PathBuf {
+    rom_path.with_extension("sav")
+}
@@ -0,0 +1,195 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const SENSOR_WIDTH: usize = 128;
+pub const SENSOR_HEIGHT: usize = 112;
+
+/// Where the Pocket Camera's sensor image comes from. Each call returns the
+/// next frame as `SENSOR_WIDTH` × `SENSOR_HEIGHT` bytes of luminance,
+/// row-major, 0 being black. Frames of any other size are padded with black
+/// or cut short.
+pub trait CameraInput {
+    fn frame(&mut self) -> Vec<u8>;
+}
+
+impl<F: FnMut() -> Vec<u8>> CameraInput for F {
+    fn frame(&mut self) -> Vec<u8> {
+        self()
+    }
+}
+
+/// The same image on every capture.
+pub struct StillImage {
+    pixels: Vec<u8>,
+}
+
+impl StillImage {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self { pixels: read_pgm(path.as_ref())? })
+    }
+}
+
+impl CameraInput for StillImage {
+    fn frame(&mut self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+}
+
+/// Every `.pgm` file in a directory, in file name order, one per capture.
+/// Wraps around after the last frame.
+pub struct FrameDirectory {
+    frames: Vec<Vec<u8>>,
+    next: usize,
+}
+
+impl FrameDirectory {
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut paths: Vec<_> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<_>>()?;
+        paths.retain(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("pgm")));
+        paths.sort();
+
+        let frames = paths.iter().map(|p| read_pgm(p)).collect::<io::Result<Vec<_>>>()?;
+        if frames.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no .pgm frames in directory"));
+        }
+
+        Ok(Self { frames, next: 0 })
+    }
+}
+
+impl CameraInput for FrameDirectory {
+    fn frame(&mut self) -> Vec<u8> {
+        let frame = self.frames[self.next].clone();
+        self.next = (self.next + 1) % self.frames.len();
+        frame
+    }
+}
+
+/// Reads a binary (P5) or plain (P2) greyscale netpbm image and scales it to
+/// the sensor resolution.
+fn read_pgm(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    decode_pgm(&data)
+        .map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {msg}", path.display())))
+}
+
+fn decode_pgm(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut pos = 0;
+
+    let magic = pgm_field(data, &mut pos).ok_or("missing magic number")?;
+    let width = pgm_number(data, &mut pos).ok_or("bad width")?;
+    let height = pgm_number(data, &mut pos).ok_or("bad height")?;
+    let max = pgm_number(data, &mut pos)
+        .filter(|&m| m > 0 && m < 0x10000)
+        .ok_or("bad maximum value")?;
+
+    let size = width.checked_mul(height).filter(|&n| n > 0).ok_or("bad image size")?;
+    let body = &data[(pos + 1).min(data.len())..];
+
+    let pixels: Vec<usize> = match magic {
+        b"P5" => {
+            let sample_bytes = if max < 0x100 { 1 } else { 2 };
+            if body.len() / sample_bytes < size {
+                return Err("truncated image data");
+            }
+            if sample_bytes == 1 {
+                body[..size].iter().map(|&b| b as usize).collect()
+            } else {
+                body.chunks_exact(2).take(size).map(|c| (c[0] as usize) << 8 | c[1] as usize).collect()
+            }
+        }
+        b"P2" => {
+            // Every sample takes at least a digit and a separator.
+            if body.len() < size.saturating_mul(2) - 1 {
+                return Err("truncated image data");
+            }
+            let mut pixels = Vec::with_capacity(size);
+            while let Some(v) = pgm_number(data, &mut pos) {
+                pixels.push(v);
+            }
+            pixels
+        }
+        _ => return Err("not a greyscale PGM image"),
+    };
+
+    if pixels.len() < size {
+        return Err("truncated image data");
+    }
+
+    let mut scaled = Vec::with_capacity(SENSOR_WIDTH * SENSOR_HEIGHT);
+    for y in 0..SENSOR_HEIGHT {
+        for x in 0..SENSOR_WIDTH {
+            let p = pixels[y * height / SENSOR_HEIGHT * width + x * width / SENSOR_WIDTH];
+            scaled.push((p.min(max) * 0xFF / max) as u8);
+        }
+    }
+
+    Ok(scaled)
+}
+
+/// Next whitespace-separated header field, skipping comments that run to the
+/// end of the line.
+fn pgm_field<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    loop {
+        while *pos < data.len() && data[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if data.get(*pos) != Some(&b'#') {
+            break;
+        }
+        while *pos < data.len() && data[*pos] != b'\n' {
+            *pos += 1;
+        }
+    }
+
+    let start = *pos;
+    while *pos < data.len() && !data[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    (*pos > start).then(|| &data[start..*pos])
+}
+
+fn pgm_number(data: &[u8], pos: &mut usize) -> Option<usize> {
+    std::str::from_utf8(pgm_field(data, pos)?).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_and_scales_p5() {
+        let mut data = b"P5\n# comment\n2 2\n255\n".to_vec();
+        data.extend([0x00, 0xFF, 0x80, 0x40]);
+
+        let frame = decode_pgm(&data).unwrap();
+        assert_eq!(frame.len(), SENSOR_WIDTH * SENSOR_HEIGHT);
+        assert_eq!(frame[0], 0x00);
+        assert_eq!(frame[SENSOR_WIDTH - 1], 0xFF);
+        assert_eq!(frame[(SENSOR_HEIGHT - 1) * SENSOR_WIDTH], 0x80);
+    }
+
+    #[test]
+    fn decodes_p2() {
+        let frame = decode_pgm(b"P2 2 1 15\n0 15\n").unwrap();
+        assert_eq!(frame[0], 0x00);
+        assert_eq!(frame[SENSOR_WIDTH - 1], 0xFF);
+    }
+
+    #[test]
+    fn rejects_oversized_header() {
+        assert!(decode_pgm(b"P5 18446744073709551615 2 255\n\0\0").is_err());
+        assert!(decode_pgm(b"P5 4294967296 4294967296 255\n\0\0").is_err());
+        assert!(decode_pgm(b"P2 100000 100000 255\n0 0").is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_body() {
+        assert!(decode_pgm(b"P5 2 2 255\n\0\0\0").is_err());
+        assert!(decode_pgm(b"P5 2 2 65535\n\0\0\0\0\0\0").is_err());
+        assert!(decode_pgm(b"P5 0 2 255\n").is_err());
+    }
+}
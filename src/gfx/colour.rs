@@ -1 +1,130 @@
+/// A CGB palette entry: 15-bit BGR555, red in the low five bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Colour(u16);
 
+/// How 5-bit channels are turned into what a modern display should show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColourCorrection {
+    /// Channels scaled straight to 8 bits, which looks oversaturated.
+    #[default]
+    Raw,
+    /// The washed-out, slightly bleeding colours of the CGB screen.
+    CgbLcd,
+    /// The darker AGB screen, modelled as a gamma curve plus channel mixing.
+    AgbGamma,
+}
+
+const LCD_GAMMA: f32 = 4.0;
+const OUT_GAMMA: f32 = 2.2;
+
+impl Colour {
+    pub const WHITE: Colour = Colour(0x7FFF);
+    pub const BLACK: Colour = Colour(0x0000);
+
+    /// The four DMG shades, lightest first, as greys.
+    pub const DMG_SHADES: [Colour; 4] = [Colour(0x7FFF), Colour(0x56B5), Colour(0x294A), Colour(0x0000)];
+
+    /// DMG shade for colour `index` through a BGP/OBP palette register.
+    pub fn dmg(palette: u8, index: u8) -> Self {
+        Self::DMG_SHADES[(palette >> (index * 2) & 3) as usize]
+    }
+
+    pub fn new(bgr555: u16) -> Self {
+        Self(bgr555 & (crate::COLORS - 1))
+    }
+
+    /// From a little-endian pair as stored in palette RAM.
+    pub fn from_bytes(low: u8, high: u8) -> Self {
+        Self::new(u16::from_le_bytes([low, high]))
+    }
+
+    pub fn from_rgb555(r: u8, g: u8, b: u8) -> Self {
+        Self::new((b as u16 & 0x1F) << 10 | (g as u16 & 0x1F) << 5 | r as u16 & 0x1F)
+    }
+
+    pub fn bgr555(self) -> u16 {
+        self.0
+    }
+
+    pub fn r(self) -> u8 {
+        (self.0 & 0x1F) as u8
+    }
+
+    pub fn g(self) -> u8 {
+        (self.0 >> 5 & 0x1F) as u8
+    }
+
+    pub fn b(self) -> u8 {
+        (self.0 >> 10 & 0x1F) as u8
+    }
+
+    pub fn to_rgb888(self, correction: ColourCorrection) -> [u8; 3] {
+        let (r, g, b) = (self.r() as u32, self.g() as u32, self.b() as u32);
+        match correction {
+            ColourCorrection::Raw => [r, g, b].map(|c| (c << 3 | c >> 2) as u8),
+            ColourCorrection::CgbLcd => [
+                r * 26 + g * 4 + b * 2,
+                g * 24 + b * 8,
+                r * 6 + g * 4 + b * 22,
+            ].map(|c| (c.min(960) >> 2) as u8),
+            ColourCorrection::AgbGamma => {
+                let [lr, lg, lb] = [r, g, b].map(|c| (c as f32 / 31.0).powf(LCD_GAMMA));
+                [
+                    50.0 * lg + 255.0 * lr,
+                    30.0 * lb + 230.0 * lg + 10.0 * lr,
+                    220.0 * lb + 10.0 * lg + 50.0 * lr,
+                ].map(|c| ((c / 255.0).powf(1.0 / OUT_GAMMA) * 255.0 * 255.0 / 280.0).round() as u8)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Black, white, red, green and blue through `correction`.
+    fn primaries(correction: ColourCorrection) -> [[u8; 3]; 5] {
+        [
+            Colour::BLACK,
+            Colour::WHITE,
+            Colour::from_rgb555(31, 0, 0),
+            Colour::from_rgb555(0, 31, 0),
+            Colour::from_rgb555(0, 0, 31),
+        ].map(|c| c.to_rgb888(correction))
+    }
+
+    #[test]
+    fn raw_scales_each_channel() {
+        assert_eq!(primaries(ColourCorrection::Raw), [
+            [0, 0, 0],
+            [255, 255, 255],
+            [255, 0, 0],
+            [0, 255, 0],
+            [0, 0, 255],
+        ]);
+        assert_eq!(Colour::from_rgb555(16, 1, 0).to_rgb888(ColourCorrection::Raw), [132, 8, 0]);
+    }
+
+    #[test]
+    fn cgb_lcd_bleeds_channels_and_caps_white() {
+        assert_eq!(primaries(ColourCorrection::CgbLcd), [
+            [0, 0, 0],
+            [240, 240, 240],
+            [201, 0, 46],
+            [31, 186, 31],
+            [15, 62, 170],
+        ]);
+    }
+
+    #[test]
+    fn agb_gamma_darkens_and_mixes() {
+        assert_eq!(primaries(ColourCorrection::AgbGamma), [
+            [0, 0, 0],
+            [252, 238, 242],
+            [232, 53, 111],
+            [111, 222, 53],
+            [0, 88, 217],
+        ]);
+    }
+}
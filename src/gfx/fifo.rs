@@ -0,0 +1,410 @@
+use std::collections::VecDeque;
+use crate::gfx::framebuffer::FRAME_WIDTH;
+use crate::gfx::object::{scan_line, Object};
+use crate::gfx::renderer::Renderer;
+use crate::gfx::scanline::{
+    flipped, object_row, LCDC_BG_ENABLE, LCDC_BG_MAP, LCDC_OBJ_ENABLE, LCDC_OBJ_TALL,
+    LCDC_WINDOW_ENABLE, LCDC_WINDOW_MAP, WX_LAST_COLUMN, WX_OFFSET,
+};
+use crate::gfx::{Colour, TILE_WIDTH};
+use crate::memory::{BgAttributes, Memory};
+
+/// Dots spent on the throwaway tile fetch that starts every line.
+const WARMUP_DOTS: u32 = 6;
+/// Dots the BG fetcher takes to read a tile index and its two data bytes,
+/// after which it waits to push them into an empty FIFO.
+const FETCH_DOTS: u8 = 6;
+/// Dot of the fetch at which the tilemap is read.
+const FETCH_TILE_DOT: u8 = 2;
+const OBJ_FETCH_DOTS: u8 = 6;
+const TILEMAP_WIDTH: usize = 32;
+
+#[derive(Clone, Copy)]
+struct BgFifoPixel {
+    index: u8,
+    palette: usize,
+    priority: bool,
+}
+
+#[derive(Clone, Copy)]
+struct ObjFifoPixel {
+    index: u8,
+    /// OBP0/OBP1 on DMG, one of the eight OBJ palettes on CGB.
+    palette: usize,
+    behind_bg: bool,
+    oam_index: usize,
+}
+
+#[derive(Default)]
+struct Fetcher {
+    dot: u8,
+    /// Tilemap column to fetch next, relative to the left of the BG or window.
+    tile_x: usize,
+    row: [u8; TILE_WIDTH],
+    attributes: BgAttributes,
+}
+
+/// Draws the way the hardware does: a fetcher feeds BG or window tiles into
+/// one FIFO, fetched objects are merged into another, and one pixel is
+/// shifted out and mixed per dot. Fine scroll, the window and objects all
+/// stall the pipeline, so mode 3 lasts as long as it would on hardware and
+/// registers written mid-line take effect mid-line.
+pub struct FifoRenderer {
+    ly: u8,
+    /// Pixels output so far.
+    x: usize,
+    /// Pixels still to be dropped from the BG FIFO before output starts.
+    discard: usize,
+    warmup: u32,
+    fetcher: Fetcher,
+    bg_fifo: VecDeque<BgFifoPixel>,
+    obj_fifo: VecDeque<Option<ObjFifoPixel>>,
+    objects: Vec<Object>,
+    fetched: Vec<bool>,
+    /// Index into `objects` being fetched, and dots left on it.
+    obj_fetch: Option<(usize, u8)>,
+    wy_triggered: bool,
+    window_line: u8,
+    window_wraps: bool,
+    wraps_this_line: bool,
+    in_window: bool,
+}
+
+impl FifoRenderer {
+    pub fn new() -> Self {
+        Self {
+            ly: 0,
+            x: 0,
+            discard: 0,
+            warmup: 0,
+            fetcher: Fetcher::default(),
+            bg_fifo: VecDeque::with_capacity(TILE_WIDTH),
+            obj_fifo: VecDeque::with_capacity(TILE_WIDTH),
+            objects: Vec::new(),
+            fetched: Vec::new(),
+            obj_fetch: None,
+            wy_triggered: false,
+            window_line: 0,
+            window_wraps: false,
+            wraps_this_line: false,
+            in_window: false,
+        }
+    }
+
+    fn step_fetcher(&mut self, mem: &Memory) {
+        if self.fetcher.dot < FETCH_DOTS {
+            self.fetcher.dot += 1;
+            if self.fetcher.dot == FETCH_TILE_DOT {
+                self.fetch_tile(mem);
+            }
+        }
+
+        if self.fetcher.dot == FETCH_DOTS && self.bg_fifo.is_empty() {
+            let attributes = self.fetcher.attributes;
+            self.bg_fifo.extend(self.fetcher.row.iter().map(|&index| BgFifoPixel {
+                index,
+                palette: attributes.palette(),
+                priority: attributes.priority(),
+            }));
+            self.fetcher.dot = 0;
+            self.fetcher.tile_x += 1;
+        }
+    }
+
+    fn fetch_tile(&mut self, mem: &Memory) {
+        let lcdc = mem.lcdc();
+        let cgb = mem.model().is_cgb();
+        let (map, col, y) = if self.in_window {
+            (lcdc & LCDC_WINDOW_MAP != 0, self.fetcher.tile_x, self.window_line as usize)
+        } else {
+            let col = (mem.scx() as usize / TILE_WIDTH + self.fetcher.tile_x) % TILEMAP_WIDTH;
+            (lcdc & LCDC_BG_MAP != 0, col, self.ly.wrapping_add(mem.scy()) as usize)
+        };
+
+        let entry = mem.vram().map_entry(map as usize, col, y / 8, mem.tile_addressing(), cgb);
+        let (_, row) = flipped(entry.attributes, 0, y % 8);
+        self.fetcher.row = entry.tile.row(row);
+        if entry.attributes.flip_x() {
+            self.fetcher.row.reverse();
+        }
+        if !cgb && lcdc & LCDC_BG_ENABLE == 0 {
+            self.fetcher.row = [0; TILE_WIDTH];
+        }
+        self.fetcher.attributes = entry.attributes;
+    }
+
+    /// Switches the fetcher over to the window once output reaches WX.
+    fn check_window(&mut self, mem: &Memory) {
+        let lcdc = mem.lcdc();
+        let enabled = lcdc & LCDC_WINDOW_ENABLE != 0
+            && (mem.model().is_cgb() || lcdc & LCDC_BG_ENABLE != 0);
+        if self.in_window || !enabled || !self.wy_triggered {
+            return;
+        }
+
+        let wx = mem.wx();
+        let start = match wx as usize {
+            _ if self.wraps_this_line => 0,
+            _ if wx > WX_LAST_COLUMN => return,
+            wx => wx.saturating_sub(WX_OFFSET),
+        };
+        if self.x < start {
+            return;
+        }
+
+        self.in_window = true;
+        self.bg_fifo.clear();
+        self.fetcher = Fetcher::default();
+        // WX below 7 cuts off the window's first columns, in place of any
+        // fine scroll still left to discard.
+        self.discard = if self.wraps_this_line { 0 } else { WX_OFFSET.saturating_sub(wx as usize) };
+    }
+
+    /// The first object not yet fetched whose left edge output has reached.
+    fn next_object(&self, mem: &Memory) -> Option<usize> {
+        if mem.lcdc() & LCDC_OBJ_ENABLE == 0 {
+            return None;
+        }
+
+        (0..self.objects.len())
+            .find(|&i| !self.fetched[i] && self.objects[i].left() <= self.x as i16)
+    }
+
+    /// Objects fetched earlier keep their pixels, which gives DMG its
+    /// leftmost-wins priority; on CGB a lower OAM index always wins.
+    fn merge_object(&mut self, mem: &Memory, i: usize) {
+        let obj = self.objects[i];
+        self.fetched[i] = true;
+
+        let cgb = mem.model().is_cgb();
+        let height = if mem.lcdc() & LCDC_OBJ_TALL != 0 { 16 } else { 8 };
+        let row = object_row(mem, &obj, self.ly, height);
+        let skip = (self.x as i16 - obj.left()) as usize;
+
+        self.obj_fifo.resize(TILE_WIDTH, None);
+        for (slot, &index) in self.obj_fifo.iter_mut().zip(&row[skip.min(TILE_WIDTH)..]) {
+            if index == 0 {
+                continue;
+            }
+            let replace = match slot {
+                None => true,
+                Some(old) => old.index == 0 || cgb && obj.oam_index < old.oam_index,
+            };
+            if replace {
+                *slot = Some(ObjFifoPixel {
+                    index,
+                    palette: if cgb { obj.cgb_palette() } else { obj.dmg_palette() },
+                    behind_bg: obj.behind_bg(),
+                    oam_index: obj.oam_index,
+                });
+            }
+        }
+    }
+
+    fn shift_pixel(&mut self, mem: &Memory, out: &mut [Colour]) {
+        let Some(bg) = self.bg_fifo.pop_front() else {
+            return;
+        };
+
+        // Discarded pixels never reach the screen, so objects, which are
+        // lined up with screen positions, stay put.
+        if self.discard > 0 {
+            self.discard -= 1;
+            return;
+        }
+
+        let obj = self.obj_fifo.pop_front().flatten();
+        out[self.x] = mix(mem, bg, obj);
+        self.x += 1;
+    }
+}
+
+/// The colour of a pixel from the front of both FIFOs, by the same rules as
+/// the scanline renderer.
+fn mix(mem: &Memory, bg: BgFifoPixel, obj: Option<ObjFifoPixel>) -> Colour {
+    let lcdc = mem.lcdc();
+    let cgb = mem.model().is_cgb();
+    let bg_master = !cgb || lcdc & LCDC_BG_ENABLE != 0;
+
+    if let Some(obj) = obj.filter(|_| lcdc & LCDC_OBJ_ENABLE != 0)
+        && !(bg_master && bg.index != 0 && (obj.behind_bg || cgb && bg.priority))
+    {
+        return if cgb {
+            mem.obj_colour(obj.palette, obj.index)
+        } else {
+            Colour::dmg(mem.obp(obj.palette), obj.index)
+        };
+    }
+
+    if cgb {
+        mem.bg_colour(bg.palette, bg.index)
+    } else if lcdc & LCDC_BG_ENABLE == 0 {
+        Colour::DMG_SHADES[0]
+    } else {
+        Colour::dmg(mem.bgp(), bg.index)
+    }
+}
+
+impl Renderer for FifoRenderer {
+    fn start_line(&mut self, mem: &Memory, ly: u8) {
+        if ly == 0 {
+            self.wy_triggered = false;
+            self.window_line = 0;
+            self.window_wraps = false;
+        }
+        if ly == mem.wy() {
+            self.wy_triggered = true;
+        }
+        self.wraps_this_line = std::mem::take(&mut self.window_wraps);
+
+        let height = if mem.lcdc() & LCDC_OBJ_TALL != 0 { 16 } else { 8 };
+        self.objects = scan_line(mem.obj_attr(), ly, height);
+        self.fetched = vec![false; self.objects.len()];
+        self.obj_fetch = None;
+
+        self.ly = ly;
+        self.x = 0;
+        self.discard = (mem.scx() & 7) as usize;
+        self.warmup = WARMUP_DOTS;
+        self.fetcher = Fetcher::default();
+        self.bg_fifo.clear();
+        self.obj_fifo.clear();
+        self.in_window = false;
+    }
+
+    fn step(&mut self, mem: &Memory, out: &mut [Colour]) -> bool {
+        if self.warmup > 0 {
+            self.warmup -= 1;
+            return false;
+        }
+
+        if self.obj_fetch.is_none() && self.discard == 0 {
+            self.obj_fetch = self.next_object(mem).map(|i| (i, OBJ_FETCH_DOTS));
+        }
+
+        match self.obj_fetch {
+            // An object fetch waits for the BG fetcher to get its current
+            // tile nearly done and the FIFO to have pixels, and holds up
+            // output until it finishes.
+            Some((i, dots)) => {
+                if self.bg_fifo.is_empty() || self.fetcher.dot < FETCH_DOTS - 1 {
+                    self.step_fetcher(mem);
+                } else if dots > 1 {
+                    self.obj_fetch = Some((i, dots - 1));
+                } else {
+                    self.merge_object(mem, i);
+                    self.obj_fetch = None;
+                }
+            }
+            None => {
+                self.check_window(mem);
+                self.shift_pixel(mem, out);
+                self.step_fetcher(mem);
+            }
+        }
+
+        if self.x < FRAME_WIDTH {
+            return false;
+        }
+
+        if self.in_window {
+            self.window_line = self.window_line.wrapping_add(1);
+            self.window_wraps = mem.wx() == WX_LAST_COLUMN;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::scanline::ScanlineRenderer;
+    use crate::model::Model;
+
+    const LCDC_ON: u8 = 0x80 | LCDC_BG_ENABLE | 0x10;
+
+    fn setup() -> Memory {
+        let mut mem = crate::memory::with_model(Model::Dmg);
+        mem.write_byte(0xFF40, LCDC_ON);
+        mem
+    }
+
+    /// Dots mode 3 takes on line `ly`.
+    fn mode_3_length(mem: &Memory, ly: u8) -> u32 {
+        let mut renderer = FifoRenderer::new();
+        let mut out = [Colour::WHITE; FRAME_WIDTH];
+        renderer.start_line(mem, ly);
+        (1..).find(|_| renderer.step(mem, &mut out)).unwrap()
+    }
+
+    fn put_object(mem: &mut Memory, index: u16, x: u8) {
+        mem.write_byte(0xFE00 + index * 4, 16);
+        mem.write_byte(0xFE01 + index * 4, x);
+        mem.write_byte(0xFE02 + index * 4, 1);
+    }
+
+    #[test]
+    fn mode_3_is_172_dots_without_scroll_window_or_objects() {
+        let mem = setup();
+        assert_eq!(mode_3_length(&mem, 0), 172);
+    }
+
+    #[test]
+    fn fine_scroll_lengthens_mode_3() {
+        let mut mem = setup();
+        for scx in 0..16 {
+            mem.write_byte(0xFF43, scx);
+            assert_eq!(mode_3_length(&mem, 0), 172 + (scx as u32 & 7), "SCX {scx}");
+        }
+    }
+
+    #[test]
+    fn window_start_lengthens_mode_3() {
+        let mut mem = setup();
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_WINDOW_ENABLE);
+        mem.write_byte(0xFF4A, 0);
+        mem.write_byte(0xFF4B, 87);
+        assert_eq!(mode_3_length(&mem, 0), 172 + 6);
+    }
+
+    #[test]
+    fn each_object_fetch_lengthens_mode_3() {
+        let mut mem = setup();
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_OBJ_ENABLE);
+        // 6 dots, plus however many the BG fetcher still needs to finish
+        // its tile: 5 at the start of one, none from 5 pixels in.
+        for (x, penalty) in [(0, 11), (8, 11), (9, 10), (12, 7), (13, 6), (15, 6), (16, 11)] {
+            put_object(&mut mem, 0, x);
+            assert_eq!(mode_3_length(&mem, 0), 172 + penalty, "X {x}");
+        }
+
+        put_object(&mut mem, 0, 8);
+        put_object(&mut mem, 1, 48);
+        assert_eq!(mode_3_length(&mem, 0), 172 + 11 + 11);
+    }
+
+    #[test]
+    fn discarded_pixels_leave_objects_in_place() {
+        let mut mem = setup();
+        for row in 0..8 {
+            mem.write_byte(0x8010 + row * 2, 0xFF);
+        }
+        mem.write_byte(0xFF47, 0xE4);
+        mem.write_byte(0xFF48, 0xE4);
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_WINDOW_ENABLE | LCDC_OBJ_ENABLE);
+        mem.write_byte(0xFF4A, 0);
+        // The window cuts off 4 of its pixels, under an object at x = 0.
+        mem.write_byte(0xFF4B, 3);
+        put_object(&mut mem, 0, 8);
+
+        let mut fifo = FifoRenderer::new();
+        let mut out = [Colour::WHITE; FRAME_WIDTH];
+        fifo.start_line(&mem, 0);
+        while !fifo.step(&mem, &mut out) {}
+
+        let mut expected = [Colour::WHITE; FRAME_WIDTH];
+        ScanlineRenderer::new().render_line(&mem, 0, &mut expected);
+        assert_eq!(out, expected);
+        assert_eq!(out[7], Colour::DMG_SHADES[1]);
+    }
+}
@@ -0,0 +1,32 @@
+use crate::gfx::{Colour, ColourCorrection};
+
+pub const FRAME_WIDTH: usize = crate::SCREEN_WIDTH as usize;
+pub const FRAME_HEIGHT: usize = crate::SCREEN_HEIGHT as usize;
+
+/// One frame of finished pixels, row by row from the top left.
+pub struct Framebuffer {
+    pixels: Box<[Colour; FRAME_WIDTH * FRAME_HEIGHT]>,
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        Self { pixels: Box::new([Colour::WHITE; FRAME_WIDTH * FRAME_HEIGHT]) }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * FRAME_WIDTH + x]
+    }
+
+    pub fn row(&self, y: usize) -> &[Colour] {
+        &self.pixels[y * FRAME_WIDTH..(y + 1) * FRAME_WIDTH]
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> &mut [Colour] {
+        &mut self.pixels[y * FRAME_WIDTH..(y + 1) * FRAME_WIDTH]
+    }
+
+    /// The frame as packed RGB888, ready for a texture upload.
+    pub fn to_rgb888(&self, correction: ColourCorrection) -> Vec<u8> {
+        self.pixels.iter().flat_map(|c| c.to_rgb888(correction)).collect()
+    }
+}
@@ -1,2 +1,15 @@
 mod colour;
+mod fifo;
+mod framebuffer;
+mod object;
+mod ppu;
+mod renderer;
+mod scanline;
 mod tile;
+
+pub use colour::{Colour, ColourCorrection};
+pub use framebuffer::{Framebuffer, FRAME_HEIGHT, FRAME_WIDTH};
+pub use object::{scan_line, Object, OAM_ENTRIES, OBJECTS_PER_LINE};
+pub use ppu::{Mode, Ppu, RendererKind};
+pub use renderer::Renderer;
+pub use tile::{decode_row, Tile, TILE_BYTES, TILE_HEIGHT, TILE_WIDTH};
@@ -0,0 +1,71 @@
+pub const OAM_ENTRIES: usize = 40;
+pub const OBJECTS_PER_LINE: usize = 10;
+
+const OBJ_BYTES: usize = 4;
+const OBJ_Y_OFFSET: i16 = 16;
+const OBJ_X_OFFSET: i16 = 8;
+
+/// One OAM entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Object {
+    /// Position in OAM, which breaks priority ties.
+    pub oam_index: usize,
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub flags: u8,
+}
+
+impl Object {
+    pub fn from_oam(oam: &[u8], oam_index: usize) -> Self {
+        let bytes = &oam[oam_index * OBJ_BYTES..(oam_index + 1) * OBJ_BYTES];
+        Self { oam_index, y: bytes[0], x: bytes[1], tile: bytes[2], flags: bytes[3] }
+    }
+
+    /// Screen column of the object's left edge; negative when partly off the
+    /// left of the screen.
+    pub fn left(&self) -> i16 {
+        self.x as i16 - OBJ_X_OFFSET
+    }
+
+    pub fn top(&self) -> i16 {
+        self.y as i16 - OBJ_Y_OFFSET
+    }
+
+    /// Whether non-zero BG colours draw over the object.
+    pub fn behind_bg(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+
+    pub fn flip_y(&self) -> bool {
+        self.flags & 0x40 != 0
+    }
+
+    pub fn flip_x(&self) -> bool {
+        self.flags & 0x20 != 0
+    }
+
+    /// OBP0 or OBP1 on DMG.
+    pub fn dmg_palette(&self) -> usize {
+        (self.flags >> 4 & 1) as usize
+    }
+
+    pub fn bank(&self) -> usize {
+        (self.flags >> 3 & 1) as usize
+    }
+
+    pub fn cgb_palette(&self) -> usize {
+        (self.flags & 0x07) as usize
+    }
+}
+
+/// OAM scan: the first ten objects in OAM order that overlap line `ly`, for
+/// objects `height` pixels tall. X plays no part, so objects off the sides
+/// still use up slots.
+pub fn scan_line(oam: &[u8], ly: u8, height: u8) -> Vec<Object> {
+    (0..OAM_ENTRIES)
+        .map(|i| Object::from_oam(oam, i))
+        .filter(|obj| (obj.top()..obj.top() + height as i16).contains(&(ly as i16)))
+        .take(OBJECTS_PER_LINE)
+        .collect()
+}
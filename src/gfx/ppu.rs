@@ -0,0 +1,306 @@
+use crate::gfx::fifo::FifoRenderer;
+use crate::gfx::framebuffer::Framebuffer;
+use crate::gfx::renderer::Renderer;
+use crate::gfx::scanline::ScanlineRenderer;
+use crate::memory::{Interrupt, Memory};
+
+const DOTS_PER_LINE: u32 = 456;
+const OAM_SCAN_DOTS: u32 = 80;
+const LINES_PER_FRAME: u8 = 154;
+
+const LCDC_ENABLE: u8 = 0x80;
+const STAT_HBLANK_SOURCE: u8 = 0x08;
+const STAT_VBLANK_SOURCE: u8 = 0x10;
+const STAT_OAM_SOURCE: u8 = 0x20;
+const STAT_LYC_SOURCE: u8 = 0x40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+/// Which renderer draws during mode 3.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RendererKind {
+    /// Whole lines at once, with a fixed mode 3 length. Fast, but blind to
+    /// mid-line register writes.
+    #[default]
+    Scanline,
+    /// Dot by dot through the pixel FIFOs, with mode 3 as long as on
+    /// hardware.
+    PixelFifo,
+}
+
+/// The PPU's timing: which mode it is in on which line, reflected in LY and
+/// STAT, and the interrupts that come with it.
+pub struct Ppu {
+    line: u8,
+    dot: u32,
+    mode: Mode,
+    enabled: bool,
+    /// The ORed STAT interrupt sources. The interrupt only fires when this
+    /// goes high, so one source staying high blocks the others.
+    stat_line: bool,
+    renderer: Box<dyn Renderer>,
+    frame: Framebuffer,
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Self::with_renderer(RendererKind::default())
+    }
+
+    pub fn with_renderer(kind: RendererKind) -> Self {
+        let renderer: Box<dyn Renderer> = match kind {
+            RendererKind::Scanline => Box::new(ScanlineRenderer::new()),
+            RendererKind::PixelFifo => Box::new(FifoRenderer::new()),
+        };
+
+        Self {
+            line: 0,
+            dot: 0,
+            mode: Mode::HBlank,
+            enabled: false,
+            stat_line: false,
+            renderer,
+            frame: Framebuffer::new(),
+        }
+    }
+
+    /// The frame being drawn, complete once VBlank starts.
+    pub fn frame(&self) -> &Framebuffer {
+        &self.frame
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn line(&self) -> u8 {
+        self.line
+    }
+
+    /// Advances by `cycles` dots, one per T-cycle at single speed.
+    pub fn tick(&mut self, mem: &mut Memory, cycles: u32) {
+        if mem.lcdc() & LCDC_ENABLE == 0 {
+            if self.enabled {
+                // Turning the LCD off resets to the top of the screen.
+                self.enabled = false;
+                self.line = 0;
+                self.dot = 0;
+                self.mode = Mode::HBlank;
+                self.stat_line = false;
+                mem.set_lcd_status(0, Mode::HBlank as u8, false);
+            }
+            return;
+        }
+
+        if !self.enabled {
+            self.enabled = true;
+            self.line = 0;
+            self.dot = 0;
+            self.mode = Mode::OamScan;
+        }
+
+        // Picks up CPU writes to LYC and the STAT sources since last time.
+        self.update_stat(mem);
+
+        let mut remaining = cycles;
+        while remaining > 0 {
+            if self.mode == Mode::Drawing {
+                self.dot += 1;
+                remaining -= 1;
+                if self.renderer.step(mem, self.frame.row_mut(self.line as usize)) {
+                    self.transition(mem);
+                }
+                continue;
+            }
+
+            // A renderer may run mode 3 past where HBlank should have ended,
+            // in which case HBlank is over at once.
+            let step = self.next_transition().saturating_sub(self.dot).min(remaining);
+            self.dot += step;
+            remaining -= step;
+            if self.dot >= self.next_transition() {
+                self.transition(mem);
+            }
+        }
+    }
+
+    /// Dot within the line at which the current mode ends. Drawing instead
+    /// ends whenever the renderer finishes the line.
+    fn next_transition(&self) -> u32 {
+        match self.mode {
+            Mode::OamScan => OAM_SCAN_DOTS,
+            Mode::Drawing | Mode::HBlank | Mode::VBlank => DOTS_PER_LINE,
+        }
+    }
+
+    fn transition(&mut self, mem: &mut Memory) {
+        match self.mode {
+            Mode::OamScan => {
+                self.renderer.start_line(mem, self.line);
+                self.mode = Mode::Drawing;
+            }
+            Mode::Drawing => self.mode = Mode::HBlank,
+            Mode::HBlank | Mode::VBlank => {
+                self.dot = 0;
+                self.line = (self.line + 1) % LINES_PER_FRAME;
+                self.mode = if self.line < crate::SCREEN_HEIGHT { Mode::OamScan } else { Mode::VBlank };
+                if self.line == crate::SCREEN_HEIGHT {
+                    mem.request_interrupt(Interrupt::VBlank);
+                }
+            }
+        }
+
+        self.update_stat(mem);
+        if self.mode == Mode::HBlank {
+            mem.hblank();
+        }
+    }
+
+    fn update_stat(&mut self, mem: &mut Memory) {
+        let coincidence = self.line == mem.lyc();
+        mem.set_lcd_status(self.line, self.mode as u8, coincidence);
+
+        let stat = mem.stat();
+        let line = stat & STAT_LYC_SOURCE != 0 && coincidence
+            || match self.mode {
+                Mode::HBlank => stat & STAT_HBLANK_SOURCE != 0,
+                // Entering VBlank also trips the OAM source.
+                Mode::VBlank => stat & STAT_VBLANK_SOURCE != 0
+                    || self.line == crate::SCREEN_HEIGHT && self.dot == 0 && stat & STAT_OAM_SOURCE != 0,
+                Mode::OamScan => stat & STAT_OAM_SOURCE != 0,
+                Mode::Drawing => false,
+            };
+
+        if line && !self.stat_line {
+            mem.request_interrupt(Interrupt::Stat);
+        }
+        self.stat_line = line;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    const IF: u16 = 0xFF0F;
+    const STAT: u16 = 0xFF41;
+    const LY: u16 = 0xFF44;
+    const LYC: u16 = 0xFF45;
+
+    fn setup() -> (Ppu, Memory) {
+        let mut mem = memory::new();
+        mem.write_byte(0xFF40, LCDC_ENABLE | 0x11);
+        let mut ppu = Ppu::new();
+        ppu.tick(&mut mem, 0);
+        (ppu, mem)
+    }
+
+    fn interrupts(mem: &Memory) -> u8 {
+        mem.read_byte(IF) & 0x1F
+    }
+
+    #[test]
+    fn modes_last_80_172_and_204_dots() {
+        let (mut ppu, mut mem) = setup();
+        for (mode, dots) in [(Mode::OamScan, 80), (Mode::Drawing, 172), (Mode::HBlank, 204)] {
+            assert_eq!(ppu.mode(), mode);
+            assert_eq!(mem.read_byte(STAT) & 0x03, mode as u8);
+            ppu.tick(&mut mem, dots - 1);
+            assert_eq!(ppu.mode(), mode);
+            ppu.tick(&mut mem, 1);
+        }
+        assert_eq!((ppu.line(), ppu.mode()), (1, Mode::OamScan));
+    }
+
+    #[test]
+    fn lines_last_456_dots_and_frames_154_lines() {
+        let (mut ppu, mut mem) = setup();
+        for line in 1..LINES_PER_FRAME {
+            ppu.tick(&mut mem, DOTS_PER_LINE);
+            assert_eq!(mem.read_byte(LY), line);
+        }
+        assert_eq!(ppu.mode(), Mode::VBlank);
+
+        ppu.tick(&mut mem, DOTS_PER_LINE);
+        assert_eq!((ppu.line(), ppu.mode()), (0, Mode::OamScan));
+    }
+
+    #[test]
+    fn vblank_interrupt_at_line_144() {
+        let (mut ppu, mut mem) = setup();
+        ppu.tick(&mut mem, 144 * DOTS_PER_LINE - 1);
+        assert_eq!(interrupts(&mem) & Interrupt::VBlank.bit(), 0);
+
+        ppu.tick(&mut mem, 1);
+        assert_eq!((ppu.line(), ppu.mode()), (144, Mode::VBlank));
+        assert_ne!(interrupts(&mem) & Interrupt::VBlank.bit(), 0);
+    }
+
+    #[test]
+    fn lyc_coincidence() {
+        let (mut ppu, mut mem) = setup();
+        mem.write_byte(LYC, 5);
+        mem.write_byte(STAT, STAT_LYC_SOURCE);
+        ppu.tick(&mut mem, 5 * DOTS_PER_LINE - 1);
+        assert_eq!(mem.read_byte(STAT) & 0x04, 0);
+        assert_eq!(interrupts(&mem), 0);
+
+        ppu.tick(&mut mem, 1);
+        assert_ne!(mem.read_byte(STAT) & 0x04, 0);
+        assert_eq!(interrupts(&mem), Interrupt::Stat.bit());
+
+        ppu.tick(&mut mem, DOTS_PER_LINE);
+        assert_eq!(mem.read_byte(STAT) & 0x04, 0);
+    }
+
+    #[test]
+    fn stat_line_staying_high_blocks_interrupts() {
+        let (mut ppu, mut mem) = setup();
+        mem.write_byte(STAT, STAT_HBLANK_SOURCE | STAT_OAM_SOURCE);
+        ppu.tick(&mut mem, 0);
+        mem.write_byte(IF, 0);
+
+        ppu.tick(&mut mem, 80 + 172);
+        assert_eq!(interrupts(&mem), Interrupt::Stat.bit(), "HBlank");
+        mem.write_byte(IF, 0);
+
+        // HBlank hands straight over to OAM scan, so the line never drops.
+        ppu.tick(&mut mem, 204);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+        assert_eq!(interrupts(&mem), 0);
+
+        ppu.tick(&mut mem, 80 + 172);
+        assert_eq!(interrupts(&mem), Interrupt::Stat.bit(), "next HBlank");
+    }
+
+    /// Keeps mode 3 going past the end of the line.
+    struct Overrun(u32);
+
+    impl Renderer for Overrun {
+        fn start_line(&mut self, _mem: &Memory, _ly: u8) {
+            self.0 = 0;
+        }
+
+        fn step(&mut self, _mem: &Memory, _out: &mut [crate::gfx::Colour]) -> bool {
+            self.0 += 1;
+            self.0 == 400
+        }
+    }
+
+    #[test]
+    fn mode_3_overrunning_the_line_cuts_hblank_short() {
+        let (mut ppu, mut mem) = setup();
+        ppu.renderer = Box::new(Overrun(0));
+        ppu.tick(&mut mem, 80 + 400);
+        assert_eq!((ppu.line(), ppu.mode()), (0, Mode::HBlank));
+        ppu.tick(&mut mem, 1);
+        assert_eq!((ppu.line(), ppu.mode()), (1, Mode::OamScan));
+    }
+}
@@ -0,0 +1,13 @@
+use crate::gfx::Colour;
+use crate::memory::Memory;
+
+/// Draws the picture during mode 3. The renderer decides how long mode 3
+/// lasts by when it reports the line finished.
+pub trait Renderer {
+    /// Mode 3 is starting on line `ly`, right after OAM scan.
+    fn start_line(&mut self, mem: &Memory, ly: u8);
+
+    /// Advances one dot into the line, drawing into `out`. Returns whether
+    /// the line is complete, ending mode 3.
+    fn step(&mut self, mem: &Memory, out: &mut [Colour]) -> bool;
+}
@@ -0,0 +1,590 @@
+use std::ops::Range;
+use crate::gfx::framebuffer::FRAME_WIDTH;
+use crate::gfx::object::{scan_line, Object};
+use crate::gfx::renderer::Renderer;
+use crate::gfx::Colour;
+use crate::memory::{BgAttributes, Memory, TileAddressing};
+
+/// Mode 3 length with no fine scroll, window or objects, which this
+/// renderer always takes.
+const DRAWING_DOTS: u32 = 172;
+
+pub const LCDC_BG_ENABLE: u8 = 0x01;
+pub const LCDC_OBJ_ENABLE: u8 = 0x02;
+pub const LCDC_OBJ_TALL: u8 = 0x04;
+pub const LCDC_BG_MAP: u8 = 0x08;
+pub const LCDC_WINDOW_ENABLE: u8 = 0x20;
+pub const LCDC_WINDOW_MAP: u8 = 0x40;
+
+/// WX is offset by 7: WX=7 puts the window's first column at x=0.
+pub const WX_OFFSET: usize = 7;
+/// WX at which the window starts in the last column and, through a hardware
+/// bug, then covers the whole of the next line.
+pub const WX_LAST_COLUMN: u8 = 166;
+
+/// What the background left at a pixel, for objects to be mixed against.
+#[derive(Clone, Copy, Default)]
+pub struct BgPixel {
+    /// Colour index before the palette.
+    pub index: u8,
+    /// CGB attribute priority. LCDC bit 0 overrides it when clear.
+    pub priority: bool,
+}
+
+/// Draws a whole line at a time from the register state at the point it is
+/// called, rather than following the PPU dot by dot.
+pub struct ScanlineRenderer {
+    bg: [BgPixel; FRAME_WIDTH],
+    /// Set once LY has matched WY this frame; the window can't show before.
+    wy_triggered: bool,
+    /// Window row to draw next. Only advances on lines the window was drawn
+    /// on, so hiding it mid-frame resumes where it left off.
+    window_line: u8,
+    /// The previous line had WX=166, so the window fills this one.
+    window_wraps: bool,
+    ly: u8,
+    dots: u32,
+}
+
+impl ScanlineRenderer {
+    pub fn new() -> Self {
+        Self {
+            bg: [BgPixel::default(); FRAME_WIDTH],
+            wy_triggered: false,
+            window_line: 0,
+            window_wraps: false,
+            ly: 0,
+            dots: 0,
+        }
+    }
+
+    pub fn render_line(&mut self, mem: &Memory, ly: u8, out: &mut [Colour]) {
+        if ly == 0 {
+            self.wy_triggered = false;
+            self.window_line = 0;
+            self.window_wraps = false;
+        }
+
+        self.render_background(mem, ly, out);
+        self.render_window(mem, ly, out);
+        self.render_objects(mem, ly, out);
+    }
+
+    /// On DMG, LCDC bit 0 clear blanks the background to white. On CGB the
+    /// background is always drawn and the bit instead takes priority away
+    /// from it.
+    fn render_background(&mut self, mem: &Memory, ly: u8, out: &mut [Colour]) {
+        let lcdc = mem.lcdc();
+        if !mem.model().is_cgb() && lcdc & LCDC_BG_ENABLE == 0 {
+            out.fill(Colour::DMG_SHADES[0]);
+            self.bg.fill(BgPixel::default());
+            return;
+        }
+
+        let map = (lcdc & LCDC_BG_MAP != 0) as usize;
+        let y = ly.wrapping_add(mem.scy()) as usize;
+        let scx = mem.scx();
+        self.draw_tiles(mem, map, y, 0..FRAME_WIDTH, |x| (x as u8).wrapping_add(scx) as usize, out);
+    }
+
+    fn render_window(&mut self, mem: &Memory, ly: u8, out: &mut [Colour]) {
+        let lcdc = mem.lcdc();
+        let wraps = std::mem::take(&mut self.window_wraps);
+        if ly == mem.wy() {
+            self.wy_triggered = true;
+        }
+
+        let enabled = lcdc & LCDC_WINDOW_ENABLE != 0
+            && (mem.model().is_cgb() || lcdc & LCDC_BG_ENABLE != 0);
+        let wx = mem.wx();
+        if !enabled || !self.wy_triggered || wx > WX_LAST_COLUMN && !wraps {
+            return;
+        }
+
+        // WX below 7 starts the window off the left edge, cutting off its
+        // first columns.
+        let (start, skip) = match wx as usize {
+            _ if wraps => (0, 0),
+            wx if wx < WX_OFFSET => (0, WX_OFFSET - wx),
+            wx => (wx - WX_OFFSET, 0),
+        };
+        self.window_wraps = wx == WX_LAST_COLUMN;
+
+        let map = (lcdc & LCDC_WINDOW_MAP != 0) as usize;
+        let y = self.window_line as usize;
+        self.draw_tiles(mem, map, y, start..FRAME_WIDTH, |x| x - start + skip, out);
+        self.window_line = self.window_line.wrapping_add(1);
+    }
+
+    /// Objects drawn over the finished BG and window. Where objects overlap,
+    /// DMG favours the one furthest left and CGB the one first in OAM; the
+    /// winner's transparent pixels let the next one show through.
+    fn render_objects(&self, mem: &Memory, ly: u8, out: &mut [Colour]) {
+        let lcdc = mem.lcdc();
+        if lcdc & LCDC_OBJ_ENABLE == 0 {
+            return;
+        }
+
+        let cgb = mem.model().is_cgb();
+        let height = if lcdc & LCDC_OBJ_TALL != 0 { 16 } else { 8 };
+        let mut objects = scan_line(mem.obj_attr(), ly, height);
+        if !cgb {
+            // Stable, so equal X keeps OAM order.
+            objects.sort_by_key(|obj| obj.x);
+        }
+        let rows: Vec<_> = objects.iter().map(|obj| object_row(mem, obj, ly, height)).collect();
+
+        // On CGB, LCDC bit 0 clear puts objects over everything.
+        let bg_master = !cgb || lcdc & LCDC_BG_ENABLE != 0;
+
+        for (x, (pixel, bg)) in out.iter_mut().zip(&self.bg).enumerate() {
+            let Some((obj, index)) = objects.iter().zip(&rows).find_map(|(obj, row)| {
+                let col = usize::try_from(x as i16 - obj.left()).ok()?;
+                let index = *row.get(col)?;
+                (index != 0).then_some((obj, index))
+            }) else {
+                continue;
+            };
+
+            if bg_master && bg.index != 0 && (obj.behind_bg() || cgb && bg.priority) {
+                continue;
+            }
+
+            *pixel = if cgb {
+                mem.obj_colour(obj.cgb_palette(), index)
+            } else {
+                Colour::dmg(mem.obp(obj.dmg_palette()), index)
+            };
+        }
+    }
+
+    /// Draws screen columns `xs` from row `y` of tilemap `map`, taking the
+    /// map column for each from `source_x`.
+    fn draw_tiles(
+        &mut self,
+        mem: &Memory,
+        map: usize,
+        y: usize,
+        xs: Range<usize>,
+        source_x: impl Fn(usize) -> usize,
+        out: &mut [Colour],
+    ) {
+        let vram = mem.vram();
+        let cgb = mem.model().is_cgb();
+        let addressing = mem.tile_addressing();
+
+        let mut cached = None;
+        for x in xs {
+            let sx = source_x(x);
+            let col = sx / 8;
+            let entry = match cached {
+                Some((c, entry)) if c == col => entry,
+                _ => {
+                    let entry = vram.map_entry(map, col, y / 8, addressing, cgb);
+                    cached = Some((col, entry));
+                    entry
+                }
+            };
+
+            let attributes = entry.attributes;
+            let (tx, ty) = flipped(attributes, sx % 8, y % 8);
+            let index = entry.tile.pixel(tx, ty);
+
+            out[x] = if cgb {
+                mem.bg_colour(attributes.palette(), index)
+            } else {
+                Colour::dmg(mem.bgp(), index)
+            };
+            self.bg[x] = BgPixel { index, priority: attributes.priority() };
+        }
+    }
+}
+
+impl Renderer for ScanlineRenderer {
+    fn start_line(&mut self, _mem: &Memory, ly: u8) {
+        self.ly = ly;
+        self.dots = 0;
+    }
+
+    /// Draws the whole line on the last dot of a fixed-length mode 3.
+    fn step(&mut self, mem: &Memory, out: &mut [Colour]) -> bool {
+        self.dots += 1;
+        if self.dots < DRAWING_DOTS {
+            return false;
+        }
+
+        self.render_line(mem, self.ly, out);
+        true
+    }
+}
+
+/// The colour indices `obj` contributes to line `ly`, left to right.
+pub fn object_row(mem: &Memory, obj: &Object, ly: u8, height: u8) -> [u8; 8] {
+    let row = (ly as i16 - obj.top()) as usize;
+    let row = if obj.flip_y() { height as usize - 1 - row } else { row };
+
+    // Tall objects ignore bit 0 of the tile index; the row picks the half.
+    let tile = if height == 16 { obj.tile & 0xFE | (row / 8) as u8 } else { obj.tile };
+    let bank = if mem.model().is_cgb() { obj.bank() } else { 0 };
+    let mut pixels = mem.vram().bank(bank).tile(TileAddressing::Unsigned, tile).row(row % 8);
+    if obj.flip_x() {
+        pixels.reverse();
+    }
+    pixels
+}
+
+/// Where (`x`, `y`) within a cell lands in its tile once the attribute flips
+/// are applied.
+pub fn flipped(attributes: BgAttributes, x: usize, y: usize) -> (usize, usize) {
+    let x = if attributes.flip_x() { 7 - x } else { x };
+    let y = if attributes.flip_y() { 7 - y } else { y };
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+
+    const BLACK_TILE: u8 = 1;
+    const LCDC_ON: u8 = 0x80 | LCDC_BG_ENABLE | 0x10;
+
+    /// A DMG with a white background and a window map full of black tiles.
+    fn setup() -> Memory {
+        let mut mem = crate::memory::with_model(Model::Dmg);
+        for row in 0..8 {
+            mem.write_byte(0x8010 + row * 2, 0xFF);
+            mem.write_byte(0x8011 + row * 2, 0xFF);
+        }
+        for cell in 0x9C00..0xA000 {
+            mem.write_byte(cell, BLACK_TILE);
+        }
+        mem.write_byte(0xFF47, 0xE4);
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_WINDOW_ENABLE | LCDC_WINDOW_MAP);
+        mem.write_byte(0xFF4B, 7);
+        mem
+    }
+
+    fn render(renderer: &mut ScanlineRenderer, mem: &Memory, ly: u8) -> [Colour; FRAME_WIDTH] {
+        let mut out = [Colour::WHITE; FRAME_WIDTH];
+        renderer.render_line(mem, ly, &mut out);
+        out
+    }
+
+    /// Colour index 1 throughout.
+    const LIGHT_TILE: u8 = 2;
+    const OBJ_RED: u16 = 0x001F;
+
+    /// A CGB with the 0x9C00 map full of black tiles, BG palette 0 running
+    /// white to black and OBJ palette 0 colour 1 red.
+    fn setup_cgb() -> Memory {
+        let mut mem = crate::memory::with_model(Model::CgbE);
+        for row in 0..8 {
+            mem.write_byte(0x8010 + row * 2, 0xFF);
+            mem.write_byte(0x8011 + row * 2, 0xFF);
+            mem.write_byte(0x8020 + row * 2, 0xFF);
+        }
+        for cell in 0x9C00..0xA000 {
+            mem.write_byte(cell, BLACK_TILE);
+        }
+        mem.write_byte(0xFF68, 0x80 | 6);
+        mem.write_byte(0xFF69, 0x00);
+        mem.write_byte(0xFF69, 0x00);
+        mem.write_byte(0xFF6A, 0x80 | 2);
+        mem.write_byte(0xFF6B, OBJ_RED as u8);
+        mem.write_byte(0xFF6B, (OBJ_RED >> 8) as u8);
+        mem
+    }
+
+    fn put_object(mem: &mut Memory, index: u16, y: u8, x: u8, tile: u8, flags: u8) {
+        let addr = 0xFE00 + index * 4;
+        for (i, byte) in [y, x, tile, flags].into_iter().enumerate() {
+            mem.write_byte(addr + i as u16, byte);
+        }
+    }
+
+    #[test]
+    fn window_starts_at_wy() {
+        let mut mem = setup();
+        mem.write_byte(0xFF4A, 10);
+        let mut renderer = ScanlineRenderer::new();
+
+        for ly in 0..10 {
+            assert_eq!(render(&mut renderer, &mem, ly)[0], Colour::WHITE, "line {ly}");
+        }
+        assert_eq!(render(&mut renderer, &mem, 10)[0], Colour::BLACK);
+        assert_eq!(renderer.window_line, 1);
+    }
+
+    #[test]
+    fn wy_moved_above_ly_mid_frame_hides_window() {
+        let mut mem = setup();
+        mem.write_byte(0xFF4A, 100);
+        let mut renderer = ScanlineRenderer::new();
+
+        for ly in 0..50 {
+            render(&mut renderer, &mem, ly);
+        }
+        // LY never equals WY for the rest of the frame.
+        mem.write_byte(0xFF4A, 20);
+        for ly in 50..144 {
+            assert_eq!(render(&mut renderer, &mem, ly)[0], Colour::WHITE, "line {ly}");
+        }
+
+        // The next frame picks the new WY up.
+        for ly in 0..20 {
+            render(&mut renderer, &mem, ly);
+        }
+        assert_eq!(render(&mut renderer, &mem, 20)[0], Colour::BLACK);
+    }
+
+    #[test]
+    fn wy_moved_after_trigger_keeps_window() {
+        let mut mem = setup();
+        mem.write_byte(0xFF4A, 0);
+        let mut renderer = ScanlineRenderer::new();
+
+        render(&mut renderer, &mem, 0);
+        mem.write_byte(0xFF4A, 120);
+        assert_eq!(render(&mut renderer, &mem, 1)[0], Colour::BLACK);
+        assert_eq!(renderer.window_line, 2);
+    }
+
+    #[test]
+    fn window_line_pauses_while_hidden() {
+        let mut mem = setup();
+        mem.write_byte(0xFF4A, 0);
+        let mut renderer = ScanlineRenderer::new();
+
+        for ly in 0..5 {
+            render(&mut renderer, &mem, ly);
+        }
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_WINDOW_MAP);
+        for ly in 5..10 {
+            assert_eq!(render(&mut renderer, &mem, ly)[0], Colour::WHITE);
+        }
+        mem.write_byte(0xFF4B, WX_LAST_COLUMN + 1);
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_WINDOW_ENABLE | LCDC_WINDOW_MAP);
+        render(&mut renderer, &mem, 10);
+        assert_eq!(renderer.window_line, 5);
+
+        mem.write_byte(0xFF4B, 7);
+        render(&mut renderer, &mem, 11);
+        assert_eq!(renderer.window_line, 6);
+    }
+
+    #[test]
+    fn window_follows_wx() {
+        let mut mem = setup();
+        mem.write_byte(0xFF4A, 0);
+        mem.write_byte(0xFF4B, 87);
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!(line[79], Colour::WHITE);
+        assert_eq!(line[80], Colour::BLACK);
+    }
+
+    #[test]
+    fn wx_below_7_cuts_off_window_columns() {
+        let mut mem = setup();
+        // Only the first window column is black.
+        for cell in 0x9C00..0xA000 {
+            mem.write_byte(cell, 0);
+        }
+        mem.write_byte(0x9C00, BLACK_TILE);
+        mem.write_byte(0xFF4A, 0);
+        mem.write_byte(0xFF4B, 3);
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!(line[3], Colour::BLACK);
+        assert_eq!(line[4], Colour::WHITE);
+    }
+
+    #[test]
+    fn wx_166_fills_next_line() {
+        let mut mem = setup();
+        mem.write_byte(0xFF4A, 0);
+        mem.write_byte(0xFF4B, WX_LAST_COLUMN);
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!(line[158], Colour::WHITE);
+        assert_eq!(line[159], Colour::BLACK);
+
+        mem.write_byte(0xFF4B, WX_LAST_COLUMN + 1);
+        let line = render(&mut renderer, &mem, 1);
+        assert!(line.iter().all(|&c| c == Colour::BLACK));
+        assert_eq!(renderer.window_line, 2);
+    }
+
+    /// Background from map 0x9C00, with only cell (`col`, `row`) black.
+    fn single_black_cell(mem: &mut Memory, col: u16, row: u16) {
+        for cell in 0x9C00..0xA000 {
+            mem.write_byte(cell, 0);
+        }
+        mem.write_byte(0x9C00 + row * 32 + col, BLACK_TILE);
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_BG_MAP);
+    }
+
+    #[test]
+    fn scx_wraps_around_the_map() {
+        let mut mem = setup();
+        single_black_cell(&mut mem, 0, 0);
+        mem.write_byte(0xFF43, 250);
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!(line[5], Colour::WHITE);
+        assert_eq!(line[6], Colour::BLACK);
+        assert_eq!(line[13], Colour::BLACK);
+        assert_eq!(line[14], Colour::WHITE);
+    }
+
+    #[test]
+    fn scy_wraps_around_the_map() {
+        let mut mem = setup();
+        single_black_cell(&mut mem, 0, 0);
+        mem.write_byte(0xFF42, 252);
+        let mut renderer = ScanlineRenderer::new();
+
+        assert_eq!(render(&mut renderer, &mem, 3)[0], Colour::WHITE);
+        assert_eq!(render(&mut renderer, &mem, 4)[0], Colour::BLACK);
+        assert_eq!(render(&mut renderer, &mem, 11)[0], Colour::BLACK);
+        assert_eq!(render(&mut renderer, &mem, 12)[0], Colour::WHITE);
+    }
+
+    #[test]
+    fn lcdc_bit_0_blanks_background_on_dmg() {
+        let mut mem = setup();
+        mem.write_byte(0xFF40, (LCDC_ON | LCDC_BG_MAP) & !LCDC_BG_ENABLE);
+        let mut renderer = ScanlineRenderer::new();
+
+        assert!(render(&mut renderer, &mem, 0).iter().all(|&c| c == Colour::WHITE));
+    }
+
+    #[test]
+    fn lcdc_bit_0_only_drops_bg_priority_on_cgb() {
+        let mut mem = setup_cgb();
+        // An object behind the background in the top left corner.
+        put_object(&mut mem, 0, 16, 8, LIGHT_TILE, 0x80);
+        let mut renderer = ScanlineRenderer::new();
+
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_BG_MAP | LCDC_OBJ_ENABLE);
+        assert_eq!(render(&mut renderer, &mem, 0)[0], Colour::BLACK);
+
+        mem.write_byte(0xFF40, (LCDC_ON | LCDC_BG_MAP | LCDC_OBJ_ENABLE) & !LCDC_BG_ENABLE);
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!(line[0], Colour::new(OBJ_RED));
+        assert_eq!(line[8], Colour::BLACK);
+    }
+
+    /// Black in the top-left pixel only.
+    const CORNER_TILE: u8 = 3;
+    const LIGHT: Colour = Colour::DMG_SHADES[1];
+
+    /// `setup` with the window off, a blank background and objects on.
+    fn setup_objects() -> Memory {
+        let mut mem = setup();
+        for row in 0..8 {
+            mem.write_byte(0x8020 + row * 2, 0xFF);
+        }
+        mem.write_byte(0x8030, 0x80);
+        mem.write_byte(0x8031, 0x80);
+        mem.write_byte(0xFF48, 0xE4);
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_OBJ_ENABLE);
+        mem
+    }
+
+    #[test]
+    fn ten_objects_per_line() {
+        let mut mem = setup_objects();
+        for i in 0..11 {
+            put_object(&mut mem, i, 16, 8 + 10 * i as u8, BLACK_TILE, 0);
+        }
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        for i in 0..10 {
+            assert_eq!(line[10 * i], Colour::BLACK, "object {i}");
+        }
+        assert_eq!(line[100], Colour::WHITE);
+    }
+
+    #[test]
+    fn overlapping_objects_by_x_on_dmg() {
+        let mut mem = setup_objects();
+        put_object(&mut mem, 0, 16, 20, LIGHT_TILE, 0);
+        put_object(&mut mem, 1, 16, 16, BLACK_TILE, 0);
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!(line[12], Colour::BLACK);
+        assert_eq!(line[16], LIGHT);
+    }
+
+    #[test]
+    fn overlapping_objects_by_oam_index_on_cgb() {
+        let mut mem = setup_cgb();
+        mem.write_byte(0xFF6A, 0x80 | 6);
+        mem.write_byte(0xFF6B, 0x00);
+        mem.write_byte(0xFF6B, 0x00);
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_OBJ_ENABLE);
+        put_object(&mut mem, 0, 16, 20, LIGHT_TILE, 0);
+        put_object(&mut mem, 1, 16, 16, BLACK_TILE, 0);
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!(line[8], Colour::BLACK);
+        assert_eq!(line[12], Colour::new(OBJ_RED));
+    }
+
+    #[test]
+    fn tall_objects_pair_even_and_odd_tiles() {
+        let mut mem = setup_objects();
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_OBJ_ENABLE | LCDC_OBJ_TALL);
+        // Bit 0 of the index is ignored: the top half is tile 2.
+        put_object(&mut mem, 0, 16, 8, CORNER_TILE, 0);
+        let mut renderer = ScanlineRenderer::new();
+
+        assert_eq!(render(&mut renderer, &mem, 7)[0], LIGHT);
+        let line = render(&mut renderer, &mem, 8);
+        assert_eq!((line[0], line[1]), (Colour::BLACK, Colour::WHITE));
+        assert_eq!(render(&mut renderer, &mem, 16)[0], Colour::WHITE);
+    }
+
+    #[test]
+    fn object_flips() {
+        let mut mem = setup_objects();
+        put_object(&mut mem, 0, 16, 8, CORNER_TILE, 0x20);
+        put_object(&mut mem, 1, 16, 24, CORNER_TILE, 0x40);
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!((line[0], line[7]), (Colour::WHITE, Colour::BLACK));
+        assert_eq!(line[16], Colour::WHITE);
+        assert_eq!(render(&mut renderer, &mem, 7)[16], Colour::BLACK);
+
+        // Flipping a tall object swaps its halves too.
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_OBJ_ENABLE | LCDC_OBJ_TALL);
+        put_object(&mut mem, 1, 16, 24, LIGHT_TILE, 0x40);
+        assert_eq!(render(&mut renderer, &mem, 0)[16], Colour::WHITE);
+        assert_eq!(render(&mut renderer, &mem, 7)[16], Colour::BLACK);
+        assert_eq!(render(&mut renderer, &mem, 8)[16], LIGHT);
+        assert_eq!(render(&mut renderer, &mem, 15)[16], LIGHT);
+    }
+
+    #[test]
+    fn bg_over_obj_only_hides_behind_nonzero_bg() {
+        let mut mem = setup_objects();
+        single_black_cell(&mut mem, 0, 0);
+        mem.write_byte(0xFF40, LCDC_ON | LCDC_BG_MAP | LCDC_OBJ_ENABLE);
+        put_object(&mut mem, 0, 16, 12, LIGHT_TILE, 0x80);
+        let mut renderer = ScanlineRenderer::new();
+
+        let line = render(&mut renderer, &mem, 0);
+        assert_eq!(line[4], Colour::BLACK);
+        assert_eq!(line[8], LIGHT);
+    }
+}
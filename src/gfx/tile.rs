@@ -1,2 +1,97 @@
+pub const TILE_WIDTH: usize = 8;
+pub const TILE_HEIGHT: usize = 8;
+pub const TILE_BYTES: usize = 16;
 
+/// An 8×8 tile as colour indices 0–3, before any palette is applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tile([[u8; TILE_WIDTH]; TILE_HEIGHT]);
 
+impl Tile {
+    /// Decodes the 2bpp format: two bytes per row, the first holding the low
+    /// bit of each pixel and the second the high bit, leftmost pixel in
+    /// bit 7.
+    pub fn decode(bytes: &[u8]) -> Self {
+        let mut pixels = [[0; TILE_WIDTH]; TILE_HEIGHT];
+        for (row, pair) in pixels.iter_mut().zip(bytes[..TILE_BYTES].chunks_exact(2)) {
+            *row = decode_row(pair[0], pair[1]);
+        }
+        Self(pixels)
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.0[y][x]
+    }
+
+    pub fn row(&self, y: usize) -> [u8; TILE_WIDTH] {
+        self.0[y]
+    }
+
+    pub fn flip_x(&self) -> Self {
+        let mut pixels = self.0;
+        for row in &mut pixels {
+            row.reverse();
+        }
+        Self(pixels)
+    }
+
+    pub fn flip_y(&self) -> Self {
+        let mut pixels = self.0;
+        pixels.reverse();
+        Self(pixels)
+    }
+}
+
+/// One row of colour indices from its low and high bit planes.
+pub fn decode_row(low: u8, high: u8) -> [u8; TILE_WIDTH] {
+    std::array::from_fn(|x| {
+        let bit = 7 - x;
+        (high >> bit & 1) << 1 | low >> bit & 1
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Colour 1 down the diagonal, and colour 2 in the top right corner.
+    fn diagonal() -> Tile {
+        let mut bytes = [0; TILE_BYTES];
+        for y in 0..TILE_HEIGHT {
+            bytes[y * 2] = 0x80 >> y;
+        }
+        bytes[1] = 0x01;
+        Tile::decode(&bytes)
+    }
+
+    #[test]
+    fn rows_combine_both_bit_planes() {
+        assert_eq!(decode_row(0b1010_0000, 0b1100_0000), [3, 2, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(decode_row(0x01, 0x00), [0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(decode_row(0xFF, 0xFF), [3; TILE_WIDTH]);
+    }
+
+    #[test]
+    fn decodes_two_bytes_per_row() {
+        let tile = diagonal();
+        assert_eq!(tile.row(0), [1, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(tile.row(7), [0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(tile.pixel(3, 3), 1);
+        assert_eq!(tile.pixel(4, 3), 0);
+    }
+
+    #[test]
+    fn flips_mirror_the_pixels() {
+        let tile = diagonal();
+        let x = tile.flip_x();
+        let y = tile.flip_y();
+        for py in 0..TILE_HEIGHT {
+            for px in 0..TILE_WIDTH {
+                assert_eq!(x.pixel(px, py), tile.pixel(7 - px, py));
+                assert_eq!(y.pixel(px, py), tile.pixel(px, 7 - py));
+            }
+        }
+        assert_eq!(x.row(0), [2, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(y.row(7), [1, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(x.flip_x(), tile);
+    }
+}
@@ -1,7 +1,11 @@
+mod cartridge;
 mod memory;
+mod model;
 mod vm;
 mod gfx;
 
+use std::path::Path;
+use cartridge::{overrides_path, read_overrides, save_path, Cartridge};
 use vm::VM;
 
 const MASTER_CLOCK: u64 = 8388608;         // Hz
@@ -16,7 +20,23 @@ const VSYNC_FREQUENCY: f64 = 59.73; // Hz
 
 fn main() {
     let mut mem = memory::new();
-    mem.0[0x1000] = 0x88;
+    if let Some(path) = std::env::args().nth(1) {
+        let rom = std::fs::read(&path).expect("could not read ROM");
+        let overrides = read_overrides(&overrides_path(Path::new(&path))).expect("could not read mapper overrides");
+        let mut cartridge = Cartridge::with_overrides(rom, &overrides).expect("could not load cartridge");
+        cartridge.attach_save_file(save_path(Path::new(&path))).expect("could not read save file");
+        mem.insert_cartridge(cartridge);
+    }
     let mut vm = VM::new();
-    vm.execute(&mut mem, 0x1000);
+    match std::env::args().nth(2) {
+        Some(path) => mem.set_boot_rom(std::fs::read(path).expect("could not read boot ROM")).expect("invalid boot ROM"),
+        None => {
+            mem.skip_boot();
+            vm.skip_boot(mem.model());
+        }
+    }
+    vm.execute(&mut mem);
+    if let Some(cartridge) = mem.cartridge_mut() {
+        cartridge.close().expect("could not write save file");
+    }
 }
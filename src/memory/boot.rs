@@ -0,0 +1,56 @@
+use std::fmt;
+use crate::model::Model;
+
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BootRomError {
+    /// Neither a DMG nor a CGB boot ROM dump.
+    WrongSize(usize),
+}
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongSize(size) => write!(f, "boot ROM is {size:#X} bytes, expected {DMG_BOOT_ROM_SIZE:#X} or {CGB_BOOT_ROM_SIZE:#X}"),
+        }
+    }
+}
+
+impl std::error::Error for BootRomError {}
+
+/// IO registers as the DMG boot ROM leaves them.
+const DMG_IO: [(u16, u8); 40] = [
+    (0xFF00, 0xCF), (0xFF01, 0x00), (0xFF02, 0x7E), (0xFF04, 0xAB),
+    (0xFF05, 0x00), (0xFF06, 0x00), (0xFF07, 0xF8), (0xFF0F, 0xE1),
+    (0xFF10, 0x80), (0xFF11, 0xBF), (0xFF12, 0xF3), (0xFF13, 0xFF),
+    (0xFF14, 0xBF), (0xFF16, 0x3F), (0xFF17, 0x00), (0xFF18, 0xFF),
+    (0xFF19, 0xBF), (0xFF1A, 0x7F), (0xFF1B, 0xFF), (0xFF1C, 0x9F),
+    (0xFF1D, 0xFF), (0xFF1E, 0xBF), (0xFF20, 0xFF), (0xFF21, 0x00),
+    (0xFF22, 0x00), (0xFF23, 0xBF), (0xFF24, 0x77), (0xFF25, 0xF3),
+    (0xFF26, 0xF1), (0xFF40, 0x91), (0xFF41, 0x85), (0xFF42, 0x00),
+    (0xFF43, 0x00), (0xFF44, 0x00), (0xFF45, 0x00), (0xFF46, 0xFF),
+    (0xFF47, 0xFC), (0xFF4A, 0x00), (0xFF4B, 0x00), (0xFF50, 0xFF),
+];
+
+/// Where the CGB boot ROM leaves things differently, applied over `DMG_IO`.
+const CGB_IO: [(u16, u8); 8] = [
+    (0xFF02, 0x7F), (0xFF04, 0x00), (0xFF46, 0x00), (0xFF4D, 0x7E),
+    (0xFF4F, 0xFE), (0xFF55, 0xFF), (0xFF56, 0x3E), (0xFF70, 0xF8),
+];
+
+/// IO register values after the boot ROM of `model` hands over to the
+/// cartridge, for starting without one.
+pub fn post_boot_io(model: Model) -> Vec<(u16, u8)> {
+    let mut io = DMG_IO.to_vec();
+    if model.is_cgb() {
+        for &(addr, byte) in &CGB_IO {
+            match io.iter_mut().find(|(a, _)| *a == addr) {
+                Some(entry) => entry.1 = byte,
+                None => io.push((addr, byte)),
+            }
+        }
+    }
+    io
+}
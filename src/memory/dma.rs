@@ -0,0 +1,158 @@
+use std::ops::Range;
+
+const OAM_DMA_LENGTH: u16 = 0xA0;
+const OAM_DMA_STARTUP: u32 = 4;
+const CYCLES_PER_BYTE: u32 = 4;
+
+/// OAM DMA, started by writing the source page to 0xFF46. After a one
+/// M-cycle startup it copies one byte per M-cycle into OAM, 160 in total.
+#[derive(Default)]
+pub struct OamDma {
+    source: u16,
+    elapsed: u32,
+    running: bool,
+}
+
+impl OamDma {
+    pub fn start(&mut self, page: u8) {
+        // Pages past work RAM read through echo RAM, which the DMA unit sees
+        // as the work RAM behind it, all the way up to 0xFFxx.
+        let page = if page >= 0xE0 { page - 0x20 } else { page };
+
+        self.source = (page as u16) << 8;
+        self.elapsed = 0;
+        self.running = true;
+    }
+
+    fn copied(&self) -> u16 {
+        (self.elapsed.saturating_sub(OAM_DMA_STARTUP) / CYCLES_PER_BYTE).min(OAM_DMA_LENGTH as u32) as u16
+    }
+
+    /// Advances by `cycles` T-cycles, returning the offsets of the bytes due
+    /// to be copied.
+    pub fn advance(&mut self, cycles: u32) -> Range<u16> {
+        if !self.running {
+            return 0..0;
+        }
+
+        let before = self.copied();
+        self.elapsed += cycles;
+        let after = self.copied();
+        if after == OAM_DMA_LENGTH {
+            self.running = false;
+        }
+
+        before..after
+    }
+
+    /// Whether the transfer has the bus, locking the CPU out of everything
+    /// but HRAM and IO.
+    pub fn active(&self) -> bool {
+        self.running && self.elapsed >= OAM_DMA_STARTUP
+    }
+
+    pub fn source(&self) -> u16 {
+        self.source
+    }
+
+    /// Address the transfer is currently reading from.
+    pub fn current_source(&self) -> u16 {
+        self.source + self.copied().min(OAM_DMA_LENGTH - 1)
+    }
+}
+
+const VRAM_DMA_BLOCK: u16 = 0x10;
+
+/// CGB VRAM DMA through HDMA1–HDMA5, either general purpose (everything at
+/// once) or HBlank (one 16-byte block per HBlank).
+pub struct VRamDma {
+    source: u16,
+    dest: u16,
+    /// Blocks left to copy, minus one, as read back through HDMA5. Bit 7 is
+    /// clear while an HBlank transfer is running.
+    length: u8,
+}
+
+impl Default for VRamDma {
+    fn default() -> Self {
+        Self { source: 0, dest: 0, length: 0xFF }
+    }
+}
+
+impl VRamDma {
+    pub fn write_source_high(&mut self, byte: u8) {
+        self.source = self.source & 0x00FF | (byte as u16) << 8;
+    }
+
+    pub fn write_source_low(&mut self, byte: u8) {
+        self.source = self.source & 0xFF00 | (byte & 0xF0) as u16;
+    }
+
+    pub fn write_dest_high(&mut self, byte: u8) {
+        self.dest = self.dest & 0x00FF | ((byte & 0x1F) as u16) << 8;
+    }
+
+    pub fn write_dest_low(&mut self, byte: u8) {
+        self.dest = self.dest & 0xFF00 | (byte & 0xF0) as u16;
+    }
+
+    pub fn length(&self) -> u8 {
+        self.length
+    }
+
+    pub fn hblank_active(&self) -> bool {
+        self.length & 0x80 == 0
+    }
+
+    /// Handles a write to HDMA5, returning how many blocks to copy right
+    /// away: all of them for a general purpose transfer, none for HBlank.
+    /// Writing bit 7 clear during an HBlank transfer cancels it instead.
+    pub fn write_length(&mut self, byte: u8) -> u8 {
+        if self.hblank_active() && byte & 0x80 == 0 {
+            self.length |= 0x80;
+            return 0;
+        }
+
+        let blocks = (byte & 0x7F) + 1;
+        if byte & 0x80 != 0 {
+            self.length = byte & 0x7F;
+            0
+        } else {
+            self.length = 0xFF;
+            blocks
+        }
+    }
+
+    /// Source and destination addresses of the next block, then moves past
+    /// it. Sources in VRAM read nothing useful; sources from 0xE000 up read
+    /// external RAM.
+    pub fn next_block(&mut self) -> (u16, u16) {
+        let source = match self.source {
+            0xE000.. => self.source - 0x4000,
+            s => s,
+        };
+        let dest = 0x8000 | self.dest & 0x1FF0;
+
+        self.source = self.source.wrapping_add(VRAM_DMA_BLOCK);
+        self.dest = self.dest.wrapping_add(VRAM_DMA_BLOCK);
+        (source, dest)
+    }
+
+    /// Counts off a block copied during HBlank.
+    pub fn finish_hblank_block(&mut self) {
+        self.length = match self.length {
+            0 => 0xFF,
+            n => n - 1,
+        };
+    }
+
+    /// Whether the destination has run past the end of VRAM, which ends the
+    /// transfer.
+    pub fn dest_overflowed(&self) -> bool {
+        self.dest >= 0x2000
+    }
+
+    pub fn stop(&mut self) {
+        self.length = 0xFF;
+    }
+}
@@ -0,0 +1,94 @@
+use std::ops::RangeInclusive;
+
+/// Kind of CPU bus access a hook fires on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Opcode fetches.
+    Execute,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccessEvent {
+    pub addr: u16,
+    pub access: Access,
+    pub value: u8,
+}
+
+pub enum HookAction {
+    /// Stop the VM once the current instruction finishes.
+    Pause,
+    Callback(Box<dyn FnMut(&AccessEvent)>),
+}
+
+/// A watchpoint on a range of addresses, optionally only firing when a
+/// particular value is read, written or executed.
+pub struct Hook {
+    pub range: RangeInclusive<u16>,
+    pub access: Access,
+    pub value: Option<u8>,
+    pub action: HookAction,
+}
+
+impl Hook {
+    pub fn pause(range: RangeInclusive<u16>, access: Access) -> Self {
+        Self { range, access, value: None, action: HookAction::Pause }
+    }
+
+    pub fn callback(range: RangeInclusive<u16>, access: Access, f: impl FnMut(&AccessEvent) + 'static) -> Self {
+        Self { range, access, value: None, action: HookAction::Callback(Box::new(f)) }
+    }
+
+    pub fn with_value(self, value: u8) -> Self {
+        Self { value: Some(value), ..self }
+    }
+
+    fn matches(&self, event: &AccessEvent) -> bool {
+        self.access == event.access
+            && self.range.contains(&event.addr)
+            && self.value.is_none_or(|v| v == event.value)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HookId(u32);
+
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<(HookId, Hook)>,
+    next_id: u32,
+    paused: Option<AccessEvent>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub fn add(&mut self, hook: Hook) -> HookId {
+        let id = HookId(self.next_id);
+        self.next_id += 1;
+        self.hooks.push((id, hook));
+        id
+    }
+
+    pub fn remove(&mut self, id: HookId) {
+        self.hooks.retain(|(i, _)| *i != id);
+    }
+
+    pub fn dispatch(&mut self, event: AccessEvent) {
+        for (_, hook) in self.hooks.iter_mut().filter(|(_, h)| h.matches(&event)) {
+            match &mut hook.action {
+                HookAction::Pause => {
+                    self.paused.get_or_insert(event);
+                }
+                HookAction::Callback(f) => f(&event),
+            }
+        }
+    }
+
+    pub fn take_pause(&mut self) -> Option<AccessEvent> {
+        self.paused.take()
+    }
+}
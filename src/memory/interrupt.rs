@@ -0,0 +1,15 @@
+/// Interrupt sources, in IF/IE bit order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    Stat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    pub fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
@@ -0,0 +1,160 @@
+use crate::model::Model;
+
+/// How an IO register behaves on the bus: `read` holds the bits that always
+/// read as 1, `write` the bits the CPU can change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoMask {
+    pub read: u8,
+    pub write: u8,
+}
+
+const fn mask(read: u8, write: u8) -> Option<IoMask> {
+    Some(IoMask { read, write })
+}
+
+/// Every bit readable and writable.
+const PLAIN: Option<IoMask> = mask(0x00, 0xFF);
+/// Reads back as 0xFF whatever was written.
+const WRITE_ONLY: Option<IoMask> = mask(0xFF, 0xFF);
+
+/// Mask for the register at `addr` in 0xFF00–0xFF7F, or `None` if nothing
+/// is mapped there on `model`. Unmapped addresses read 0xFF and ignore
+/// writes.
+pub fn io_mask(model: Model, addr: u16) -> Option<IoMask> {
+    let cgb = model.is_cgb();
+    match addr {
+        // P1. No buttons are wired up yet, so the inputs read as released.
+        0xFF00 => mask(0xCF, 0x30),
+        0xFF01 => PLAIN,
+        // SC. Bit 1 selects the fast serial clock on CGB.
+        0xFF02 if cgb => mask(0x7C, 0x83),
+        0xFF02 => mask(0x7E, 0x81),
+        0xFF04..=0xFF06 => PLAIN,
+        0xFF07 => mask(0xF8, 0x07),
+        0xFF0F => mask(0xE0, 0x1F),
+
+        // Sound. Lengths and frequencies are write-only.
+        0xFF10 => mask(0x80, 0x7F),
+        0xFF11 | 0xFF16 => mask(0x3F, 0xFF),
+        0xFF12 | 0xFF17 | 0xFF21 | 0xFF22 | 0xFF24 | 0xFF25 => PLAIN,
+        0xFF13 | 0xFF18 | 0xFF1B | 0xFF1D | 0xFF20 => WRITE_ONLY,
+        0xFF14 | 0xFF19 | 0xFF1E => mask(0xBF, 0xC7),
+        0xFF1A => mask(0x7F, 0x80),
+        0xFF1C => mask(0x9F, 0x60),
+        0xFF23 => mask(0xBF, 0xC0),
+        // NR52. The channel status bits are read-only.
+        0xFF26 => mask(0x70, 0x80),
+        0xFF30..=0xFF3F => PLAIN,
+
+        0xFF40 => PLAIN,
+        // STAT. The mode and coincidence bits belong to the PPU.
+        0xFF41 => mask(0x80, 0x78),
+        0xFF42 | 0xFF43 => PLAIN,
+        0xFF44 => mask(0x00, 0x00),
+        0xFF45..=0xFF4B => PLAIN,
+
+        // KEY1. Only the switch request is writable; STOP flips the speed.
+        0xFF4D if cgb => mask(0x7E, 0x01),
+        0xFF4F if cgb => mask(0xFE, 0x01),
+        0xFF50..=0xFF54 => WRITE_ONLY,
+        0xFF55 if cgb => PLAIN,
+        // RP. Bit 1 reads 1 while no infrared light is received.
+        0xFF56 if cgb => mask(0x3E, 0xC1),
+        0xFF68 | 0xFF6A if cgb => mask(0x40, 0xBF),
+        0xFF69 | 0xFF6B if cgb => PLAIN,
+        0xFF6C if cgb => mask(0xFE, 0x01),
+        0xFF70 if cgb => mask(0xF8, 0x07),
+        0xFF72..=0xFF74 if cgb => PLAIN,
+        0xFF75 if cgb => mask(0x8F, 0x70),
+        // PCM12/PCM34, the live channel outputs.
+        0xFF76 | 0xFF77 if cgb => mask(0x00, 0x00),
+        _ => None,
+    }
+}
+
+/// Conventional name of the register at `addr`, for annotating addresses
+/// in debuggers and traces.
+pub fn io_register_name(addr: u16) -> Option<&'static str> {
+    let name = match addr {
+        0xFF00 => "P1",
+        0xFF01 => "SB",
+        0xFF02 => "SC",
+        0xFF04 => "DIV",
+        0xFF05 => "TIMA",
+        0xFF06 => "TMA",
+        0xFF07 => "TAC",
+        0xFF0F => "IF",
+        0xFF10 => "NR10",
+        0xFF11 => "NR11",
+        0xFF12 => "NR12",
+        0xFF13 => "NR13",
+        0xFF14 => "NR14",
+        0xFF16 => "NR21",
+        0xFF17 => "NR22",
+        0xFF18 => "NR23",
+        0xFF19 => "NR24",
+        0xFF1A => "NR30",
+        0xFF1B => "NR31",
+        0xFF1C => "NR32",
+        0xFF1D => "NR33",
+        0xFF1E => "NR34",
+        0xFF20 => "NR41",
+        0xFF21 => "NR42",
+        0xFF22 => "NR43",
+        0xFF23 => "NR44",
+        0xFF24 => "NR50",
+        0xFF25 => "NR51",
+        0xFF26 => "NR52",
+        0xFF30..=0xFF3F => "WAVE",
+        0xFF40 => "LCDC",
+        0xFF41 => "STAT",
+        0xFF42 => "SCY",
+        0xFF43 => "SCX",
+        0xFF44 => "LY",
+        0xFF45 => "LYC",
+        0xFF46 => "DMA",
+        0xFF47 => "BGP",
+        0xFF48 => "OBP0",
+        0xFF49 => "OBP1",
+        0xFF4A => "WY",
+        0xFF4B => "WX",
+        0xFF4D => "KEY1",
+        0xFF4F => "VBK",
+        0xFF50 => "BANK",
+        0xFF51 => "HDMA1",
+        0xFF52 => "HDMA2",
+        0xFF53 => "HDMA3",
+        0xFF54 => "HDMA4",
+        0xFF55 => "HDMA5",
+        0xFF56 => "RP",
+        0xFF68 => "BCPS",
+        0xFF69 => "BCPD",
+        0xFF6A => "OCPS",
+        0xFF6B => "OCPD",
+        0xFF6C => "OPRI",
+        0xFF70 => "SVBK",
+        0xFF76 => "PCM12",
+        0xFF77 => "PCM34",
+        0xFFFF => "IE",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_registers_and_leaves_holes_unnamed() {
+        assert_eq!(io_register_name(0xFF00), Some("P1"));
+        assert_eq!(io_register_name(0xFF40), Some("LCDC"));
+        assert_eq!(io_register_name(0xFF3F), Some("WAVE"));
+        assert_eq!(io_register_name(0xFF70), Some("SVBK"));
+        assert_eq!(io_register_name(0xFFFF), Some("IE"));
+
+        assert_eq!(io_register_name(0xFF03), None);
+        assert_eq!(io_register_name(0xFF15), None);
+        assert_eq!(io_register_name(0xFF80), None);
+    }
+}
@@ -1,5 +1,16 @@
-use std::ops::{Index, IndexMut, Range, RangeInclusive};
-use crate::memory::vram::{as_vram, VRam};
+use std::cell::RefCell;
+use std::io;
+use std::ops::{Range, RangeInclusive};
+use crate::cartridge::Cartridge;
+use crate::memory::boot::{post_boot_io, BootRomError, CGB_BOOT_ROM_SIZE, DMG_BOOT_ROM_SIZE};
+use crate::memory::dma::{OamDma, VRamDma};
+use crate::memory::io::io_mask;
+use crate::memory::region::Region;
+use crate::memory::interrupt::Interrupt;
+use crate::memory::hooks::{Access, AccessEvent, Hook, HookId, Hooks};
+use crate::memory::vram::{as_vram, TileAddressing, VRam, VRAM_BANK_SIZE};
+use crate::gfx::Colour;
+use crate::model::Model;
 
 const ROM_BANK_00: Range<usize> = 0x0000..0x4000;
 const ROM_BANK_01_NN: Range<usize> = 0x4000..0x8000;
@@ -28,91 +39,1175 @@ const VRAM_DMA: RangeInclusive<usize> = 0xFF51..=0xFF55;
 const BG_OBJ_PALETTES: RangeInclusive<usize> = 0xFF68..=0xFF6B;
 const WRAM_BANK_SELECT: RangeInclusive<usize> = 0xFF70..=0xFF70;
 
-pub struct Memory(pub [u8; 1<<16]);
+const DIV: usize = 0xFF04;
+const OAM_DMA: usize = 0xFF46;
+const KEY1: usize = 0xFF4D;
+const LCDC: usize = 0xFF40;
+const STAT: usize = 0xFF41;
+const LY: usize = 0xFF44;
+const LYC: usize = 0xFF45;
+const IF: usize = 0xFF0F;
+const SCY: usize = 0xFF42;
+const SCX: usize = 0xFF43;
+const BGP: usize = 0xFF47;
+const OBP0: usize = 0xFF48;
+const WY: usize = 0xFF4A;
+const WX: usize = 0xFF4B;
+const BCPS: usize = 0xFF68;
+const OCPS: usize = 0xFF6A;
+
+const PALETTE_RAM_SIZE: usize = 64;
+const PALETTE_AUTO_INCREMENT: u8 = 0x80;
+
+const LCDC_ENABLE: u8 = 0x80;
+const STAT_MODE: u8 = 0x03;
+const KEY1_DOUBLE_SPEED: u8 = 0x80;
+
+/// T-cycles in a frame, how often a dirty save file is written back.
+const FRAME_CYCLES: u32 = 70224;
+
+pub const WRAM_BANK_SIZE: usize = 0x1000;
+pub const WRAM_BANKS: usize = 8;
+
+pub struct Memory {
+    mem: [u8; 1<<16],
+    wram: Box<[u8; WRAM_BANKS * WRAM_BANK_SIZE]>,
+    vram: Box<[u8; 2 * VRAM_BANK_SIZE]>,
+    model: Model,
+    cartridge: Option<Cartridge>,
+    oam_dma: OamDma,
+    vram_dma: VRamDma,
+    bg_palettes: [u8; PALETTE_RAM_SIZE],
+    obj_palettes: [u8; PALETTE_RAM_SIZE],
+    cpu_stall: u32,
+    flush_cycles: u32,
+    boot_rom: Option<Vec<u8>>,
+    hooks: RefCell<Hooks>,
+    /// Mirrors whether `hooks` has anything in it, so accesses skip the
+    /// registry entirely when nothing is being watched.
+    hooked: bool,
+    ppu_blocking: bool,
+}
+
+/// The separate buses the CPU and DMA units contend for.
+#[derive(PartialEq, Eq)]
+enum Bus {
+    External,
+    Wram,
+    Video,
+    Internal,
+}
 
 pub fn new() -> Memory {
-    Memory([0; 1<<16])
+    with_model(Model::default())
+}
+
+pub fn with_model(model: Model) -> Memory {
+    Memory {
+        mem: [0; 1<<16],
+        wram: Box::new([0; WRAM_BANKS * WRAM_BANK_SIZE]),
+        vram: Box::new([0; 2 * VRAM_BANK_SIZE]),
+        model,
+        cartridge: None,
+        oam_dma: OamDma::default(),
+        vram_dma: VRamDma::default(),
+        bg_palettes: [0xFF; PALETTE_RAM_SIZE],
+        obj_palettes: [0xFF; PALETTE_RAM_SIZE],
+        cpu_stall: 0,
+        flush_cycles: 0,
+        boot_rom: None,
+        hooks: RefCell::default(),
+        hooked: false,
+        ppu_blocking: true,
+    }
 }
 
 impl Memory {
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.cartridge = Some(cartridge);
+    }
+
+    pub fn cartridge(&self) -> Option<&Cartridge> {
+        self.cartridge.as_ref()
+    }
+
+    pub fn cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.cartridge.as_mut()
+    }
+
+    /// Maps a boot ROM over the cartridge until 0xFF50 is written. A DMG
+    /// boot ROM covers 0x0000–0x00FF; a CGB one also covers 0x0200–0x08FF,
+    /// leaving the cartridge header visible in between. Other sizes aren't
+    /// boot ROM dumps and are rejected.
+    pub fn set_boot_rom(&mut self, rom: Vec<u8>) -> Result<(), BootRomError> {
+        if !matches!(rom.len(), DMG_BOOT_ROM_SIZE | CGB_BOOT_ROM_SIZE) {
+            return Err(BootRomError::WrongSize(rom.len()));
+        }
+        self.boot_rom = Some(rom);
+        Ok(())
+    }
+
+    /// Sets up IO registers the way the boot ROM would have left them.
+    pub fn skip_boot(&mut self) {
+        self.boot_rom = None;
+        for (addr, byte) in post_boot_io(self.model) {
+            self.mem[addr as usize] = byte;
+        }
+    }
+
+    fn boot_rom_byte(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..0x0100 | 0x0200..0x0900 => self.boot_rom.as_ref()?.get(addr as usize).copied(),
+            _ => None,
+        }
+    }
+
+    /// Which region `addr` falls in as the bus currently maps it. CGB-only
+    /// registers count as plain IO on DMG.
+    pub fn region_of(&self, addr: u16) -> Region {
+        if self.boot_rom_byte(addr).is_some() {
+            return Region::BootRom;
+        }
+
+        let a = addr as usize;
+        let cgb = self.model.is_cgb();
+        match a {
+            _ if ROM_BANK_00.contains(&a) => Region::RomBank00,
+            _ if ROM_BANK_01_NN.contains(&a) => Region::RomBank01NN,
+            _ if VRAM.contains(&a) => Region::VRam,
+            _ if EXT_RAM.contains(&a) => Region::ExtRam,
+            _ if WRAM.contains(&a) => Region::WRam,
+            _ if WRAM_SWITCHABLE.contains(&a) => Region::WRamSwitchable,
+            _ if ECHO_RAM.contains(&a) => Region::EchoRam,
+            _ if OBJ_ATTR.contains(&a) => Region::ObjAttr,
+            _ if UNUSABLE.contains(&a) => Region::Unusable,
+            _ if JOYPAD.contains(&a) => Region::Joypad,
+            _ if SERIAL.contains(&a) => Region::Serial,
+            _ if TIMER_DIVIDER.contains(&a) => Region::TimerDivider,
+            _ if INTERRUPTS.contains(&a) => Region::Interrupts,
+            _ if AUDIO.contains(&a) => Region::Audio,
+            _ if WAVE_PATTERN.contains(&a) => Region::WavePattern,
+            _ if LCD_CONTROL.contains(&a) => Region::LcdControl,
+            _ if BOOT_ROM_MAPPING_CONTROL.contains(&a) => Region::BootRomMappingControl,
+            _ if VRAM_BANK_SELECT.contains(&a) && cgb => Region::VRamBankSelect,
+            _ if VRAM_DMA.contains(&a) && cgb => Region::VRamDma,
+            _ if BG_OBJ_PALETTES.contains(&a) && cgb => Region::BgObjPalettes,
+            _ if WRAM_BANK_SELECT.contains(&a) && cgb => Region::WRamBankSelect,
+            _ if IO.contains(&a) => Region::Io,
+            _ if HRAM.contains(&a) => Region::HRam,
+            _ => Region::InterruptEnable,
+        }
+    }
+
+    /// Advances clocks that live outside the CPU by `cycles` T-cycles, and
+    /// once a frame writes back the cartridge's save file if it changed, so
+    /// a crash loses at most a frame of progress. Fails if that write does.
+    pub fn tick(&mut self, cycles: u32) -> io::Result<()> {
+        if let Some(cartridge) = &mut self.cartridge {
+            cartridge.tick(cycles);
+        }
+
+        let source = self.oam_dma.source();
+        for i in self.oam_dma.advance(cycles) {
+            self.mem[OBJ_ATTR.start + i as usize] = self.read_bus(source + i);
+        }
+
+        self.flush_cycles += cycles;
+        if self.flush_cycles < FRAME_CYCLES {
+            return Ok(());
+        }
+        self.flush_cycles %= FRAME_CYCLES;
+        match &mut self.cartridge {
+            Some(cartridge) => cartridge.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// M-cycles the CPU has to sit out while VRAM DMA holds the bus. Resets
+    /// the count.
+    pub fn take_cpu_stall(&mut self) -> u32 {
+        std::mem::take(&mut self.cpu_stall)
+    }
+
+    /// Copies the next HBlank DMA block, if a transfer is running. Called by
+    /// the PPU on entering HBlank.
+    pub fn hblank(&mut self) {
+        if !self.vram_dma.hblank_active() {
+            return;
+        }
+
+        self.vram_dma_block();
+        self.vram_dma.finish_hblank_block();
+        if self.vram_dma.dest_overflowed() {
+            self.vram_dma.stop();
+        }
+    }
+
+    fn start_vram_dma(&mut self, byte: u8) {
+        for _ in 0..self.vram_dma.write_length(byte) {
+            if self.vram_dma.dest_overflowed() {
+                break;
+            }
+            self.vram_dma_block();
+        }
+
+        // Started during HBlank or with the LCD off, the first block goes
+        // right away.
+        let lcd_off = self.mem[LCDC] & LCDC_ENABLE == 0;
+        if self.vram_dma.hblank_active() && (lcd_off || self.mem[STAT] & STAT_MODE == 0) {
+            self.hblank();
+        }
+    }
+
+    fn vram_dma_block(&mut self) {
+        let (source, dest) = self.vram_dma.next_block();
+        for i in 0..0x10 {
+            let byte = match source + i {
+                0x8000..0xA000 => 0xFF,
+                s => self.read_bus(s),
+            };
+            self.write_bus(dest + i, byte);
+        }
+
+        // A block takes the same time at either speed, which is twice the
+        // M-cycles in double speed mode.
+        self.cpu_stall += if self.mem[KEY1] & KEY1_DOUBLE_SPEED != 0 { 16 } else { 8 };
+    }
+
+    fn bus(&self, addr: u16) -> Bus {
+        match addr {
+            0x8000..0xA000 => Bus::Video,
+            0xC000..0xFE00 if self.model.is_cgb() => Bus::Wram,
+            0xFE00.. => Bus::Internal,
+            _ => Bus::External,
+        }
+    }
+
+    /// While OAM DMA runs, what the CPU sees instead of `addr`: OAM is
+    /// unreadable, and the bus the transfer is reading from yields the
+    /// transfer's byte. HRAM, IO and the other buses are unaffected.
+    fn oam_dma_conflict(&self, addr: u16) -> Option<u8> {
+        if !self.oam_dma.active() {
+            return None;
+        }
+
+        let source = self.oam_dma.current_source();
+        match self.bus(addr) {
+            Bus::Internal if (addr as usize) < IO.start => Some(0xFF),
+            Bus::Internal => None,
+            bus if bus == self.bus(source) => Some(self.read_bus(source)),
+            _ => None,
+        }
+    }
+
+    /// Watches CPU accesses matching `hook`. DMA transfers don't trigger
+    /// hooks.
+    pub fn add_hook(&mut self, hook: Hook) -> HookId {
+        self.hooked = true;
+        self.hooks.get_mut().add(hook)
+    }
+
+    pub fn remove_hook(&mut self, id: HookId) {
+        let hooks = self.hooks.get_mut();
+        hooks.remove(id);
+        self.hooked = !hooks.is_empty();
+    }
+
+    /// The access that tripped a pausing hook since the last call, if any.
+    pub fn take_pause(&mut self) -> Option<AccessEvent> {
+        self.hooks.get_mut().take_pause()
+    }
+
+    fn run_hooks(&self, addr: u16, access: Access, value: u8) {
+        if self.hooked {
+            self.hooks.borrow_mut().dispatch(AccessEvent { addr, access, value });
+        }
+    }
+
     pub fn read_byte(&self, addr: u16) -> u8 {
-        self[addr]
+        let byte = self.cpu_read(addr);
+        self.run_hooks(addr, Access::Read, byte);
+        byte
     }
 
-    pub fn read_word(&self, addr: u16) -> u16 {
-        let bytes = &self[addr..=addr+1];
+    /// Reads an opcode for the CPU to execute, as `read_byte` but firing
+    /// execute hooks rather than read hooks.
+    pub fn fetch_byte(&self, addr: u16) -> u8 {
+        let byte = self.cpu_read(addr);
+        self.run_hooks(addr, Access::Execute, byte);
+        byte
+    }
+
+    fn cpu_read(&self, addr: u16) -> u8 {
+        if let Some(byte) = self.oam_dma_conflict(addr) {
+            return byte;
+        }
 
-        u16::from_le_bytes(bytes.try_into().expect("must read two bytes"))
+        if self.ppu_blocked(addr) {
+            return 0xFF;
+        }
+
+        self.read_bus(addr)
+    }
+
+    fn read_bus(&self, addr: u16) -> u8 {
+        if let Some(byte) = self.boot_rom_byte(addr) {
+            return byte;
+        }
+
+        match (addr as usize, &self.cartridge) {
+            (0x0000..0x8000, Some(cartridge)) => cartridge.read_rom(addr),
+            (0xA000..0xC000, Some(cartridge)) => cartridge.read_ram(addr - EXT_RAM.start as u16),
+            (0x8000..0xA000, _) => self.vram[self.vram_offset(addr)],
+            (0xC000..0xE000, _) => self.wram[self.wram_offset(addr)],
+            (0xE000..0xFE00, _) => self.wram[self.wram_offset(addr - 0x2000)],
+            (0xFEA0..0xFF00, _) => self.read_unusable(addr),
+            (0xFF55, _) if self.model.is_cgb() => self.vram_dma.length(),
+            (0xFF69, _) if self.model.is_cgb() => self.bg_palettes[self.palette_index(BCPS)],
+            (0xFF6B, _) if self.model.is_cgb() => self.obj_palettes[self.palette_index(OCPS)],
+            (0xFF00..0xFF80, _) => self.read_io(addr),
+            _ => self.mem[addr as usize],
+        }
+    }
+
+    /// Whether the PPU currently has OAM to itself, during OAM scan and
+    /// drawing.
+    fn oam_blocked(&self) -> bool {
+        self.ppu_blocking && self.mem[LCDC] & LCDC_ENABLE != 0 && self.mem[STAT] & STAT_MODE >= 2
+    }
+
+    /// Whether the PPU currently has VRAM to itself, while drawing.
+    fn vram_blocked(&self) -> bool {
+        self.ppu_blocking && self.mem[LCDC] & LCDC_ENABLE != 0 && self.mem[STAT] & STAT_MODE == 3
+    }
+
+    fn ppu_blocked(&self, addr: u16) -> bool {
+        let addr = addr as usize;
+        VRAM.contains(&addr) && self.vram_blocked() || OBJ_ATTR.contains(&addr) && self.oam_blocked()
+    }
+
+    /// With `true`, the default, the CPU is locked out of VRAM during mode 3
+    /// and out of OAM during modes 2 and 3, as on hardware. `false` lets
+    /// debuggers read and write both through the CPU bus at any time.
+    pub fn set_ppu_blocking(&mut self, enabled: bool) {
+        self.ppu_blocking = enabled;
+    }
+
+    /// Where an access to 0xFEA0–0xFEFF lands on CGB revisions that back
+    /// the region with memory; later revisions and DMG have none.
+    fn unusable_addr(&self, addr: u16) -> Option<u16> {
+        match self.model {
+            Model::CgbC => Some(addr & !0x18),
+            Model::CgbD if addr >= 0xFEC0 => Some(addr | 0xF0),
+            Model::CgbD => Some(addr),
+            Model::Dmg | Model::CgbE | Model::Agb => None,
+        }
+    }
+
+    fn read_unusable(&self, addr: u16) -> u8 {
+        if self.oam_blocked() {
+            return 0xFF;
+        }
+
+        match self.model {
+            Model::Dmg => 0x00,
+            // The high nibble of the low address byte, twice: 0xFEAx reads 0xAA.
+            Model::CgbE | Model::Agb => (addr as u8 & 0xF0) | (addr as u8 >> 4),
+            _ => self.unusable_addr(addr).map_or(0xFF, |a| self.mem[a as usize]),
+        }
+    }
+
+    fn write_unusable(&mut self, addr: u16, byte: u8) {
+        if self.oam_blocked() {
+            return;
+        }
+
+        if let Some(a) = self.unusable_addr(addr) {
+            self.mem[a as usize] = byte;
+        }
+    }
+
+    pub fn read_word(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr.wrapping_add(1))])
     }
 
     pub fn write_byte(&mut self, addr: u16, byte: u8) {
-        self[addr] = byte
+        self.run_hooks(addr, Access::Write, byte);
+        if self.oam_dma_conflict(addr).is_some() || self.ppu_blocked(addr) {
+            return;
+        }
+
+        self.write_bus(addr, byte);
+    }
+
+    fn write_bus(&mut self, addr: u16, byte: u8) {
+        match (addr as usize, &mut self.cartridge) {
+            (0x0000..0x8000, Some(cartridge)) => cartridge.write_rom(addr, byte),
+            (0xA000..0xC000, Some(cartridge)) => cartridge.write_ram(addr - EXT_RAM.start as u16, byte),
+            (0x8000..0xA000, _) => self.vram[self.vram_offset(addr)] = byte,
+            (0xC000..0xE000, _) => self.wram[self.wram_offset(addr)] = byte,
+            (0xE000..0xFE00, _) => self.wram[self.wram_offset(addr - 0x2000)] = byte,
+            (0xFEA0..0xFF00, _) => self.write_unusable(addr, byte),
+            (OAM_DMA, _) => {
+                self.mem[OAM_DMA] = byte;
+                self.oam_dma.start(byte);
+            }
+            (0xFF50, _) => {
+                if byte != 0 {
+                    self.boot_rom = None;
+                }
+            }
+            (0xFF51..=0xFF55, _) if !self.model.is_cgb() => {}
+            (0xFF51, _) => self.vram_dma.write_source_high(byte),
+            (0xFF52, _) => self.vram_dma.write_source_low(byte),
+            (0xFF53, _) => self.vram_dma.write_dest_high(byte),
+            (0xFF54, _) => self.vram_dma.write_dest_low(byte),
+            (0xFF55, _) => self.start_vram_dma(byte),
+            (0xFF69, _) if self.model.is_cgb() => {
+                self.bg_palettes[self.palette_index(BCPS)] = byte;
+                self.advance_palette_index(BCPS);
+            }
+            (0xFF6B, _) if self.model.is_cgb() => {
+                self.obj_palettes[self.palette_index(OCPS)] = byte;
+                self.advance_palette_index(OCPS);
+            }
+            (DIV, _) => self.mem[DIV] = 0,
+            (0xFF00..0xFF80, _) => self.write_io(addr, byte),
+            _ => self.mem[addr as usize] = byte,
+        }
+    }
+
+    /// Palette RAM byte selected by BCPS or OCPS.
+    fn palette_index(&self, spec: usize) -> usize {
+        self.mem[spec] as usize & (PALETTE_RAM_SIZE - 1)
+    }
+
+    fn advance_palette_index(&mut self, spec: usize) {
+        let byte = self.mem[spec];
+        if byte & PALETTE_AUTO_INCREMENT != 0 {
+            self.mem[spec] = byte & PALETTE_AUTO_INCREMENT | (byte + 1) & (PALETTE_RAM_SIZE as u8 - 1);
+        }
+    }
+
+    fn read_io(&self, addr: u16) -> u8 {
+        match io_mask(self.model, addr) {
+            Some(mask) => self.mem[addr as usize] | mask.read,
+            None => 0xFF,
+        }
+    }
+
+    fn write_io(&mut self, addr: u16, byte: u8) {
+        if let Some(mask) = io_mask(self.model, addr) {
+            let reg = &mut self.mem[addr as usize];
+            *reg = *reg & !mask.write | byte & mask.write;
+        }
     }
 
     pub fn write_word(&mut self, addr: u16, word: u16) {
-        self[addr] = word as u8;
-        self[addr+1] = (word >> 8) as u8;
+        self.write_byte(addr, word as u8);
+        self.write_byte(addr.wrapping_add(1), (word >> 8) as u8);
     }
 
     pub fn rom_bank_00(&self) -> &[u8] {
-        &self.0[ROM_BANK_00]
+        match &self.cartridge {
+            Some(cartridge) => cartridge.rom_bank_00(),
+            None => &self.mem[ROM_BANK_00],
+        }
     }
 
     pub fn rom_bank_01_nn(&self) -> &[u8] {
-        &self.0[ROM_BANK_01_NN]
+        match &self.cartridge {
+            Some(cartridge) => cartridge.rom_bank_01_nn(),
+            None => &self.mem[ROM_BANK_01_NN],
+        }
     }
 
-    pub fn vram(&mut self) -> VRam {
-        as_vram(&self.0[VRAM])
+    pub fn lcdc(&self) -> u8 {
+        self.mem[LCDC]
     }
 
+    pub fn stat(&self) -> u8 {
+        self.mem[STAT]
+    }
+
+    pub fn lyc(&self) -> u8 {
+        self.mem[LYC]
+    }
+
+    /// Sets LY and the read-only low bits of STAT (mode and coincidence),
+    /// which the CPU can't write.
+    pub fn set_lcd_status(&mut self, ly: u8, mode: u8, coincidence: bool) {
+        self.mem[LY] = ly;
+        self.mem[STAT] = self.mem[STAT] & !0x07 | mode & STAT_MODE | (coincidence as u8) << 2;
+    }
+
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.mem[IF] |= interrupt.bit();
+    }
+
+    pub fn scy(&self) -> u8 {
+        self.mem[SCY]
+    }
+
+    pub fn scx(&self) -> u8 {
+        self.mem[SCX]
+    }
+
+    pub fn bgp(&self) -> u8 {
+        self.mem[BGP]
+    }
+
+    /// OBP0 or OBP1.
+    pub fn obp(&self, palette: usize) -> u8 {
+        self.mem[OBP0 + (palette & 1)]
+    }
+
+    pub fn wy(&self) -> u8 {
+        self.mem[WY]
+    }
+
+    pub fn wx(&self) -> u8 {
+        self.mem[WX]
+    }
+
+    /// Colour `index` of CGB BG palette `palette`.
+    pub fn bg_colour(&self, palette: usize, index: u8) -> Colour {
+        palette_colour(&self.bg_palettes, palette, index)
+    }
+
+    /// Colour `index` of CGB OBJ palette `palette`.
+    pub fn obj_colour(&self, palette: usize, index: u8) -> Colour {
+        palette_colour(&self.obj_palettes, palette, index)
+    }
+
+    /// How BG and window tile indices address tile data, per LCDC bit 4.
+    pub fn tile_addressing(&self) -> TileAddressing {
+        TileAddressing::from_lcdc(self.mem[LCDC])
+    }
+
+    pub fn vram(&self) -> VRam<'_> {
+        as_vram(self.vram_bank(0), self.vram_bank(1))
+    }
+
+    /// VRAM bank mapped at 0x8000–0x9FFF, selected by VBK on CGB.
+    pub fn vram_bank_select(&self) -> usize {
+        if !self.model.is_cgb() {
+            return 0;
+        }
+
+        self.mem[*VRAM_BANK_SELECT.start()] as usize & 0x01
+    }
+
+    /// Either 8 KiB VRAM bank, regardless of which is mapped.
+    pub fn vram_bank(&self, bank: usize) -> &[u8] {
+        let start = (bank & 1) * VRAM_BANK_SIZE;
+        &self.vram[start..start + VRAM_BANK_SIZE]
+    }
+
+    pub fn vram_bank_mut(&mut self, bank: usize) -> &mut [u8] {
+        let start = (bank & 1) * VRAM_BANK_SIZE;
+        &mut self.vram[start..start + VRAM_BANK_SIZE]
+    }
+
+    fn vram_offset(&self, addr: u16) -> usize {
+        self.vram_bank_select() * VRAM_BANK_SIZE + addr as usize - VRAM.start
+    }
+
+    /// With a cartridge inserted this is its entire external RAM, all banks.
     pub fn ext_ram(&self) -> &[u8] {
-        &self.0[EXT_RAM]
+        match &self.cartridge {
+            Some(cartridge) => cartridge.ext_ram(),
+            None => &self.mem[EXT_RAM],
+        }
     }
 
     pub fn wram(&self) -> &[u8] {
-        &self.0[WRAM]
+        self.wram_bank(0)
     }
 
     pub fn wram_switchable(&self) -> &[u8] {
-        &self.0[WRAM_SWITCHABLE]
+        self.wram_bank(self.wram_bank_select())
+    }
+
+    /// WRAM bank mapped at 0xD000–0xDFFF. SVBK selects it on CGB, where
+    /// selecting bank 0 gives bank 1; DMG only has bank 1.
+    pub fn wram_bank_select(&self) -> usize {
+        if !self.model.is_cgb() {
+            return 1;
+        }
+
+        (self.mem[*WRAM_BANK_SELECT.start()] as usize & (WRAM_BANKS - 1)).max(1)
+    }
+
+    /// Any of the eight 4 KiB WRAM banks, regardless of which is mapped.
+    /// `bank` wraps around like SVBK does.
+    pub fn wram_bank(&self, bank: usize) -> &[u8] {
+        let start = (bank & (WRAM_BANKS - 1)) * WRAM_BANK_SIZE;
+        &self.wram[start..start + WRAM_BANK_SIZE]
+    }
+
+    pub fn wram_bank_mut(&mut self, bank: usize) -> &mut [u8] {
+        let start = (bank & (WRAM_BANKS - 1)) * WRAM_BANK_SIZE;
+        &mut self.wram[start..start + WRAM_BANK_SIZE]
+    }
+
+    fn wram_offset(&self, addr: u16) -> usize {
+        let addr = addr as usize;
+        if WRAM.contains(&addr) {
+            addr - WRAM.start
+        } else {
+            self.wram_bank_select() * WRAM_BANK_SIZE + addr - WRAM_SWITCHABLE.start
+        }
     }
 
     pub fn obj_attr(&self) -> &[u8] {
-        &self.0[OBJ_ATTR]
+        &self.mem[OBJ_ATTR]
     }
 
     pub fn io(&self) -> &[u8] {
-        &self.0[IO]
+        &self.mem[IO]
     }
 
     pub fn hram(&self) -> &[u8] {
-        &self.0[HRAM]
+        &self.mem[HRAM]
     }
 
     pub fn interrupt_enable_register(&self) -> &[u8] {
-        &self.0[INTERRUPT_ENABLE..]
+        &self.mem[INTERRUPT_ENABLE..]
     }
 }
 
-impl Index<u16> for Memory {
-    type Output = u8;
+fn palette_colour(ram: &[u8; PALETTE_RAM_SIZE], palette: usize, index: u8) -> Colour {
+    let offset = (palette & 7) * 8 + (index as usize & 3) * 2;
+    Colour::from_bytes(ram[offset], ram[offset + 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
 
-    fn index(&self, idx: u16) -> &Self::Output {
-        self.0.index(idx as usize)
+    #[test]
+    fn echo_ram_mirrors_wram() {
+        let mut mem = new();
+        mem.write_byte(0xC123, 0x42);
+        assert_eq!(mem.read_byte(0xE123), 0x42);
+
+        mem.write_byte(0xFDFF, 0x24);
+        assert_eq!(mem.read_byte(0xDDFF), 0x24);
     }
-}
 
-impl IndexMut<u16> for Memory {
-    fn index_mut(&mut self, index: u16) -> &mut Self::Output {
-        self.0.index_mut(index as usize)
+    #[test]
+    fn echo_ram_follows_wram_bank() {
+        let mut mem = with_model(Model::CgbE);
+        mem.write_byte(0xFF70, 3);
+        mem.write_byte(0xD010, 0x33);
+        mem.write_byte(0xFF70, 5);
+        mem.write_byte(0xF010, 0x55);
+
+        assert_eq!(mem.read_byte(0xF010), 0x55);
+        assert_eq!(mem.wram_bank(3)[0x10], 0x33);
+        assert_eq!(mem.wram_bank(5)[0x10], 0x55);
+    }
+
+    #[test]
+    fn wram_bank_wraps_like_svbk() {
+        let mut mem = with_model(Model::CgbE);
+        mem.wram_bank_mut(10)[0x20] = 0x22;
+        assert_eq!(mem.wram_bank(2)[0x20], 0x22);
+        assert_eq!(mem.wram_bank(usize::MAX)[0x20], mem.wram_bank(7)[0x20]);
+    }
+
+    #[test]
+    fn echo_ram_stops_before_oam() {
+        let mut mem = new();
+        mem.write_byte(0xDE00, 0x11);
+        mem.write_byte(0xFE00, 0x22);
+        assert_eq!(mem.read_byte(0xDE00), 0x11);
+        assert_eq!(mem.obj_attr()[0], 0x22);
+    }
+
+    #[test]
+    fn unusable_reads_zero_on_dmg() {
+        let mut mem = with_model(Model::Dmg);
+        mem.write_byte(0xFEA0, 0x12);
+        assert_eq!(mem.read_byte(0xFEA0), 0x00);
+        assert_eq!(mem.read_byte(0xFEFF), 0x00);
+    }
+
+    #[test]
+    fn unusable_reads_ff_while_oam_blocked() {
+        for model in [Model::Dmg, Model::CgbC, Model::CgbD, Model::CgbE, Model::Agb] {
+            let mut mem = with_model(model);
+            mem.write_byte(LCDC as u16, LCDC_ENABLE);
+            mem.mem[STAT] = 0x02;
+            assert_eq!(mem.read_byte(0xFEB0), 0xFF, "{model:?}");
+            mem.mem[STAT] = 0x03;
+            assert_eq!(mem.read_byte(0xFEB0), 0xFF, "{model:?}");
+        }
+    }
+
+    #[test]
+    fn unusable_repeats_address_nibble_on_cgb_e_and_agb() {
+        for model in [Model::CgbE, Model::Agb] {
+            let mut mem = with_model(model);
+            mem.write_byte(0xFEA5, 0x00);
+            assert_eq!(mem.read_byte(0xFEA5), 0xAA);
+            assert_eq!(mem.read_byte(0xFEB0), 0xBB);
+            assert_eq!(mem.read_byte(0xFEFF), 0xFF);
+        }
+    }
+
+    #[test]
+    fn unusable_is_memory_on_cgb_d() {
+        let mut mem = with_model(Model::CgbD);
+        mem.write_byte(0xFEA4, 0x12);
+        assert_eq!(mem.read_byte(0xFEA4), 0x12);
+
+        // 0xFEC0 onwards is a single 16-byte area, repeated.
+        mem.write_byte(0xFEC5, 0x34);
+        assert_eq!(mem.read_byte(0xFED5), 0x34);
+        assert_eq!(mem.read_byte(0xFEF5), 0x34);
+    }
+
+    #[test]
+    fn unusable_is_mirrored_memory_on_early_cgb() {
+        let mut mem = with_model(Model::CgbC);
+        mem.write_byte(0xFEA1, 0x56);
+        assert_eq!(mem.read_byte(0xFEA9), 0x56);
+        assert_eq!(mem.read_byte(0xFEB1), 0x56);
+        assert_eq!(mem.read_byte(0xFEB9), 0x56);
+    }
+
+    /// 0xFF00–0xFF7F read back after writing 0x00 to each of them.
+    const DMG_IO_ZEROED: [u8; 0x80] = [
+        0xCF, 0x00, 0x7E, 0xFF, 0x00, 0x00, 0x00, 0xF8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xE0,
+        0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF, 0xBF, 0xFF,
+        0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00, 0x70, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+
+    const CGB_IO_ZEROED: [u8; 0x80] = [
+        0xCF, 0x00, 0x7C, 0xFF, 0x00, 0x00, 0x00, 0xF8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xE0,
+        0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF, 0xBF, 0xFF,
+        0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00, 0x70, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x7E, 0xFF, 0xFE,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x3E, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x40, 0x00, 0x40, 0x00, 0xFE, 0xFF, 0xFF, 0xFF,
+        0xF8, 0xFF, 0x00, 0x00, 0x00, 0x8F, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+
+    fn assert_io_zeroed(model: Model, expected: &[u8; 0x80]) {
+        let mut mem = with_model(model);
+        for addr in 0xFF00..0xFF80 {
+            mem.write_byte(addr, 0x00);
+        }
+        for (i, &byte) in expected.iter().enumerate() {
+            let addr = 0xFF00 + i as u16;
+            assert_eq!(mem.read_byte(addr), byte, "{model:?} {addr:#06X}");
+        }
+    }
+
+    #[test]
+    fn io_unused_bits_read_one_on_dmg() {
+        assert_io_zeroed(Model::Dmg, &DMG_IO_ZEROED);
+    }
+
+    #[test]
+    fn io_unused_bits_read_one_on_cgb() {
+        assert_io_zeroed(Model::CgbE, &CGB_IO_ZEROED);
+    }
+
+    #[test]
+    fn io_read_only_bits_ignore_writes() {
+        let mut mem = with_model(Model::CgbE);
+        mem.mem[STAT] = 0x03;
+        mem.write_byte(STAT as u16, 0x00);
+        assert_eq!(mem.read_byte(STAT as u16), 0x83);
+
+        mem.mem[0xFF44] = 0x90;
+        mem.write_byte(0xFF44, 0x12);
+        assert_eq!(mem.read_byte(0xFF44), 0x90);
+
+        mem.write_byte(KEY1 as u16, 0xFF);
+        assert_eq!(mem.read_byte(KEY1 as u16), 0x7F);
+    }
+
+    #[test]
+    fn div_resets_on_write() {
+        let mut mem = new();
+        mem.mem[DIV] = 0xAB;
+        mem.write_byte(DIV as u16, 0x55);
+        assert_eq!(mem.read_byte(DIV as u16), 0x00);
+    }
+
+    #[test]
+    fn vram_blocked_during_drawing() {
+        let mut mem = new();
+        mem.write_byte(0x8000, 0x12);
+        mem.write_byte(LCDC as u16, LCDC_ENABLE);
+        mem.mem[STAT] = 0x03;
+        assert_eq!(mem.read_byte(0x8000), 0xFF);
+        mem.write_byte(0x8000, 0x34);
+
+        mem.mem[STAT] = 0x02;
+        assert_eq!(mem.read_byte(0x8000), 0x12);
+    }
+
+    #[test]
+    fn oam_blocked_during_scan_and_drawing() {
+        let mut mem = new();
+        mem.write_byte(0xFE00, 0x12);
+        mem.write_byte(LCDC as u16, LCDC_ENABLE);
+        for mode in [0x02, 0x03] {
+            mem.mem[STAT] = mode;
+            assert_eq!(mem.read_byte(0xFE00), 0xFF);
+            mem.write_byte(0xFE00, 0x34);
+        }
+
+        mem.mem[STAT] = 0x00;
+        assert_eq!(mem.read_byte(0xFE00), 0x12);
+    }
+
+    #[test]
+    fn ppu_blocking_can_be_overridden() {
+        let mut mem = new();
+        mem.write_byte(0x8000, 0x12);
+        mem.write_byte(LCDC as u16, LCDC_ENABLE);
+        mem.mem[STAT] = 0x03;
+        mem.set_ppu_blocking(false);
+        assert_eq!(mem.read_byte(0x8000), 0x12);
+        assert_eq!(mem.read_byte(0xFE00), 0x00);
+    }
+
+    fn fill_page(mem: &mut Memory, page: u16) {
+        for i in 0..0xA0 {
+            mem.write_byte(page + i, i as u8 ^ 0x5A);
+        }
+    }
+
+    #[test]
+    fn oam_dma_copies_in_640_cycles_after_startup() {
+        let mut mem = new();
+        fill_page(&mut mem, 0xC100);
+        mem.write_byte(0xFF46, 0xC1);
+
+        mem.tick(4).unwrap();
+        assert_eq!(mem.obj_attr()[0], 0x00);
+        mem.tick(636).unwrap();
+        assert_eq!(mem.obj_attr()[0x9E], 0x9E ^ 0x5A);
+        assert_eq!(mem.obj_attr()[0x9F], 0x00);
+        mem.tick(4).unwrap();
+
+        let expected: Vec<u8> = (0..0xA0).map(|i| i as u8 ^ 0x5A).collect();
+        assert_eq!(mem.obj_attr()[..0xA0], expected[..]);
+        assert_eq!(mem.read_byte(0xFE00), 0x5A, "OAM is readable again");
+    }
+
+    #[test]
+    fn oam_dma_conflicts_on_the_source_bus() {
+        let mut mem = new();
+        fill_page(&mut mem, 0xC100);
+        mem.write_byte(0xD000, 0x11);
+        mem.write_byte(0x8000, 0x22);
+        mem.write_byte(0xFF46, 0xC1);
+        mem.tick(4 + 4 * 3).unwrap();
+
+        // Work RAM shares the external bus on DMG, VRAM has its own.
+        assert_eq!(mem.read_byte(0xD000), 3 ^ 0x5A);
+        assert_eq!(mem.read_byte(0x8000), 0x22);
+        assert_eq!(mem.read_byte(0xFE00), 0xFF);
+
+        mem.write_byte(0xD000, 0x33);
+        mem.tick(640).unwrap();
+        assert_eq!(mem.read_byte(0xD000), 0x11, "writes are dropped during DMA");
+    }
+
+    #[test]
+    fn hram_stays_usable_during_oam_dma() {
+        let mut mem = new();
+        mem.write_byte(0xFF46, 0xC1);
+        mem.tick(8).unwrap();
+        mem.write_byte(0xFF80, 0x42);
+        assert_eq!(mem.read_byte(0xFF80), 0x42);
+    }
+
+    #[test]
+    fn oam_dma_from_echo_pages_reads_wram() {
+        let mut mem = new();
+        fill_page(&mut mem, 0xDE00);
+        mem.write_byte(0xFF46, 0xFE);
+        mem.tick(4 + 640).unwrap();
+        assert_eq!(mem.obj_attr()[0x10], 0x10 ^ 0x5A);
+    }
+
+    fn start_hdma(mem: &mut Memory, source: u16, dest: u16, length: u8) {
+        mem.write_byte(0xFF51, (source >> 8) as u8);
+        mem.write_byte(0xFF52, source as u8);
+        mem.write_byte(0xFF53, (dest >> 8) as u8);
+        mem.write_byte(0xFF54, dest as u8);
+        mem.write_byte(0xFF55, length);
+    }
+
+    #[test]
+    fn general_dma_masks_addresses() {
+        let mut mem = with_model(Model::CgbE);
+        for i in 0..0x20 {
+            mem.write_byte(0xC100 + i, i as u8);
+        }
+
+        // The low source and destination nibbles are dropped, as are the top
+        // three destination bits.
+        start_hdma(&mut mem, 0xC10F, 0xF20F, 0x01);
+        assert_eq!(mem.vram_bank(0)[0x1200..0x1220], (0..0x20).collect::<Vec<u8>>()[..]);
+        assert_eq!(mem.read_byte(0xFF55), 0xFF);
+        assert_eq!(mem.take_cpu_stall(), 2 * 8);
+        assert_eq!(mem.take_cpu_stall(), 0);
+    }
+
+    #[test]
+    fn hblank_dma_counts_down_in_hdma5() {
+        let mut mem = with_model(Model::CgbE);
+        mem.write_byte(LCDC as u16, LCDC_ENABLE);
+        mem.mem[STAT] = 0x03;
+        start_hdma(&mut mem, 0xC000, 0x8000, 0x82);
+        assert_eq!(mem.read_byte(0xFF55), 0x02);
+
+        mem.hblank();
+        assert_eq!(mem.read_byte(0xFF55), 0x01);
+        mem.hblank();
+        mem.hblank();
+        assert_eq!(mem.read_byte(0xFF55), 0xFF);
+        mem.hblank();
+        assert_eq!(mem.take_cpu_stall(), 3 * 8);
+    }
+
+    #[test]
+    fn hblank_dma_cancelled_by_clearing_bit_7() {
+        let mut mem = with_model(Model::CgbE);
+        mem.write_byte(LCDC as u16, LCDC_ENABLE);
+        mem.mem[STAT] = 0x03;
+        start_hdma(&mut mem, 0xC000, 0x8000, 0x85);
+        mem.hblank();
+
+        mem.write_byte(0xFF55, 0x00);
+        assert_eq!(mem.read_byte(0xFF55), 0x84, "blocks left, with bit 7 set");
+        mem.hblank();
+        assert_eq!(mem.take_cpu_stall(), 8);
+    }
+
+    #[test]
+    fn hblank_dma_with_lcd_off_copies_first_block_at_once() {
+        let mut mem = with_model(Model::CgbE);
+        mem.write_byte(0xC000, 0x42);
+        start_hdma(&mut mem, 0xC000, 0x8000, 0x81);
+        assert_eq!(mem.vram_bank(0)[0], 0x42);
+        assert_eq!(mem.read_byte(0xFF55), 0x00);
+    }
+
+    #[test]
+    fn vram_dma_stalls_twice_as_long_in_double_speed() {
+        let mut mem = with_model(Model::CgbE);
+        mem.mem[KEY1] |= KEY1_DOUBLE_SPEED;
+        start_hdma(&mut mem, 0xC000, 0x8000, 0x03);
+        assert_eq!(mem.take_cpu_stall(), 4 * 16);
+    }
+
+    #[test]
+    fn boot_rom_must_be_dmg_or_cgb_sized() {
+        let mut mem = new();
+        assert_eq!(mem.set_boot_rom(vec![0; 0x200]), Err(BootRomError::WrongSize(0x200)));
+        assert_eq!(mem.region_of(0x0000), Region::RomBank00);
+        assert!(mem.set_boot_rom(vec![0; DMG_BOOT_ROM_SIZE]).is_ok());
+    }
+
+    #[test]
+    fn boot_rom_only_overlays_its_ranges() {
+        let mut mem = with_model(Model::CgbE);
+        mem.set_boot_rom(vec![0x31; CGB_BOOT_ROM_SIZE]).unwrap();
+        assert_eq!(mem.region_of(0x00FF), Region::BootRom);
+        assert_eq!(mem.region_of(0x0100), Region::RomBank00);
+        assert_eq!(mem.region_of(0x0200), Region::BootRom);
+        assert_eq!(mem.region_of(0x08FF), Region::BootRom);
+        assert_eq!(mem.region_of(0x0900), Region::RomBank00);
+        assert_eq!(mem.region_of(0x8000), Region::VRam);
+
+        mem.write_byte(0xFF50, 0x01);
+        assert_eq!(mem.region_of(0x0000), Region::RomBank00);
+    }
+
+    #[test]
+    fn dirty_saves_are_written_once_a_frame() {
+        let path = std::env::temp_dir().join(format!("immolator-frame-{}.sav", std::process::id()));
+        let mut rom = vec![0; 0x8000];
+        rom[0x147] = 0x1B;
+        rom[0x149] = 0x02;
+        let mut cartridge = Cartridge::new(rom).unwrap();
+        cartridge.attach_save_file(&path).unwrap();
+        let mut mem = new();
+        mem.insert_cartridge(cartridge);
+
+        mem.tick(FRAME_CYCLES).unwrap();
+        assert!(!path.exists(), "nothing changed yet");
+
+        mem.write_byte(0x0000, 0x0A);
+        mem.write_byte(0xA000, 0x12);
+        mem.tick(FRAME_CYCLES - 1).unwrap();
+        assert!(!path.exists());
+        mem.tick(1).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[0], 0x12);
+
+        std::fs::remove_file(&path).unwrap();
+        mem.tick(FRAME_CYCLES).unwrap();
+        assert!(!path.exists(), "already written");
+
+        drop(mem);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn vbk_switches_vram_banks_on_cgb() {
+        let mut mem = with_model(Model::CgbE);
+        mem.write_byte(0x8000, 0x11);
+        mem.write_byte(0xFF4F, 0x01);
+        assert_eq!(mem.read_byte(0xFF4F), 0xFF);
+        assert_eq!(mem.read_byte(0x8000), 0x00);
+        mem.write_byte(0x9FFF, 0x22);
+
+        mem.write_byte(0xFF4F, 0xFE);
+        assert_eq!(mem.read_byte(0xFF4F), 0xFE);
+        assert_eq!(mem.read_byte(0x8000), 0x11);
+        assert_eq!(mem.read_byte(0x9FFF), 0x00);
+        assert_eq!(mem.vram_bank(1)[VRAM_BANK_SIZE - 1], 0x22);
+    }
+
+    #[test]
+    fn vbk_is_ignored_on_dmg() {
+        let mut mem = with_model(Model::Dmg);
+        mem.write_byte(0xFF4F, 0x01);
+        assert_eq!(mem.read_byte(0xFF4F), 0xFF);
+        assert_eq!(mem.vram_bank_select(), 0);
+
+        mem.write_byte(0x8000, 0x33);
+        assert_eq!(mem.vram_bank(0)[0], 0x33);
+        assert_eq!(mem.vram_bank(1)[0], 0x00);
+    }
+
+    /// A callback that records every access it fires on, and the record.
+    fn recorder() -> (Rc<RefCell<Vec<AccessEvent>>>, impl FnMut(&AccessEvent)) {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        (events, move |e: &AccessEvent| sink.borrow_mut().push(*e))
+    }
+
+    #[test]
+    fn hooks_fire_on_their_range_and_access() {
+        let mut mem = new();
+        let (reads, f) = recorder();
+        mem.add_hook(Hook::callback(0xC000..=0xC0FF, Access::Read, f));
+        let (writes, f) = recorder();
+        mem.add_hook(Hook::callback(0xC000..=0xC0FF, Access::Write, f));
+        let (executes, f) = recorder();
+        mem.add_hook(Hook::callback(0xC000..=0xC0FF, Access::Execute, f));
+
+        mem.write_byte(0xC010, 0x42);
+        mem.write_byte(0xC100, 0x43);
+        mem.read_byte(0xC010);
+        mem.read_byte(0xBFFF);
+        mem.fetch_byte(0xC0FF);
+
+        let event = |addr, access, value| AccessEvent { addr, access, value };
+        assert_eq!(*writes.borrow(), [event(0xC010, Access::Write, 0x42)]);
+        assert_eq!(*reads.borrow(), [event(0xC010, Access::Read, 0x42)]);
+        assert_eq!(*executes.borrow(), [event(0xC0FF, Access::Execute, 0x00)]);
+    }
+
+    #[test]
+    fn value_filtered_hooks_only_fire_on_that_value() {
+        let mut mem = new();
+        let (writes, f) = recorder();
+        mem.add_hook(Hook::callback(0xFF40..=0xFF40, Access::Write, f).with_value(0x91));
+
+        mem.write_byte(0xFF40, 0x11);
+        mem.write_byte(0xFF40, 0x91);
+        mem.write_byte(0xFF41, 0x91);
+
+        assert_eq!(writes.borrow().len(), 1);
+        assert_eq!(writes.borrow()[0].value, 0x91);
+    }
+
+    #[test]
+    fn pause_hooks_report_the_first_access() {
+        let mut mem = new();
+        mem.add_hook(Hook::pause(0xC000..=0xCFFF, Access::Write));
+        assert_eq!(mem.take_pause(), None);
+
+        mem.write_byte(0xC001, 0x01);
+        mem.write_byte(0xC002, 0x02);
+        assert_eq!(mem.take_pause(), Some(AccessEvent { addr: 0xC001, access: Access::Write, value: 0x01 }));
+        assert_eq!(mem.take_pause(), None);
+    }
+
+    #[test]
+    fn removing_the_last_hook_unhooks_memory() {
+        let mut mem = new();
+        let (reads, f) = recorder();
+        let first = mem.add_hook(Hook::callback(0xC000..=0xC000, Access::Read, f));
+        let second = mem.add_hook(Hook::pause(0xC000..=0xC000, Access::Read));
+        assert!(mem.hooked);
+
+        mem.remove_hook(first);
+        assert!(mem.hooked);
+        mem.remove_hook(second);
+        assert!(!mem.hooked);
+
+        mem.read_byte(0xC000);
+        assert!(reads.borrow().is_empty());
+        assert_eq!(mem.take_pause(), None);
     }
-}
 
-impl Index<RangeInclusive<u16>> for Memory {
-    type Output = [u8];
+    #[test]
+    fn regions_change_at_their_boundaries() {
+        let mem = with_model(Model::CgbE);
+        for (addr, region) in [
+            (0x3FFF, Region::RomBank00),
+            (0x4000, Region::RomBank01NN),
+            (0x7FFF, Region::RomBank01NN),
+            (0x8000, Region::VRam),
+            (0xA000, Region::ExtRam),
+            (0xC000, Region::WRam),
+            (0xD000, Region::WRamSwitchable),
+            (0xE000, Region::EchoRam),
+            (0xFDFF, Region::EchoRam),
+            (0xFE00, Region::ObjAttr),
+            (0xFEA0, Region::Unusable),
+            (0xFF00, Region::Joypad),
+            (0xFF03, Region::Io),
+            (0xFF26, Region::Audio),
+            (0xFF27, Region::Io),
+            (0xFF4B, Region::LcdControl),
+            (0xFF4F, Region::VRamBankSelect),
+            (0xFF55, Region::VRamDma),
+            (0xFF70, Region::WRamBankSelect),
+            (0xFF7F, Region::Io),
+            (0xFF80, Region::HRam),
+            (0xFFFE, Region::HRam),
+            (0xFFFF, Region::InterruptEnable),
+        ] {
+            assert_eq!(mem.region_of(addr), region, "{addr:#06X}");
+        }
 
-    fn index(&self, idx: RangeInclusive<u16>) -> &Self::Output {
-        self.0.index((*idx.start() as usize)..=(*idx.end() as usize))
+        let dmg = with_model(Model::Dmg);
+        assert_eq!(dmg.region_of(0xFF4F), Region::Io);
+        assert_eq!(dmg.region_of(0xFF70), Region::Io);
     }
 }
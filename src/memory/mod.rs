@@ -1,5 +1,16 @@
+mod boot;
+mod dma;
+mod hooks;
+mod interrupt;
+mod io;
 mod memory;
+mod region;
 mod vram;
 
+pub use hooks::{Access, AccessEvent, Hook, HookAction, HookId};
+pub use interrupt::Interrupt;
+pub use io::io_register_name;
 pub use memory::Memory;
-pub use memory::new;
+pub use memory::{new, with_model};
+pub use region::Region;
+pub use vram::{BgAttributes, Block, MapEntry, TileAddressing, Tilemap, VRam, VRamBank};
@@ -0,0 +1,30 @@
+/// What an address on the CPU bus maps to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Region {
+    BootRom,
+    RomBank00,
+    RomBank01NN,
+    VRam,
+    ExtRam,
+    WRam,
+    WRamSwitchable,
+    EchoRam,
+    ObjAttr,
+    Unusable,
+    Joypad,
+    Serial,
+    TimerDivider,
+    Interrupts,
+    Audio,
+    WavePattern,
+    LcdControl,
+    VRamBankSelect,
+    BootRomMappingControl,
+    VRamDma,
+    BgObjPalettes,
+    WRamBankSelect,
+    /// Any other IO register, or an unmapped hole between them.
+    Io,
+    HRam,
+    InterruptEnable,
+}
@@ -1,37 +1,286 @@
-pub struct VRam<'mem>(pub &'mem [u8]);
+use crate::gfx::{Tile, TILE_BYTES};
 
-const TILE_SIZE: usize = 16;
+pub const VRAM_BANK_SIZE: usize = 0x2000;
+
+const TILE_SIZE: usize = TILE_BYTES;
 const TILES_PER_BLOCK: usize = 128;
 const BLOCK_SIZE: usize = TILE_SIZE * TILES_PER_BLOCK;
 const TILEMAP_SIZE: usize = 1024;
 
-pub fn as_vram(slice: &[u8]) -> VRam {
-    VRam(slice)
+const TILEMAP_WIDTH: usize = 32;
+
+/// How tile indices in a tilemap map to tile data, chosen by LCDC bit 4.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileAddressing {
+    /// Indices 0–255 from 0x8000.
+    Unsigned,
+    /// Indices -128–127 around 0x9000.
+    Signed,
+}
+
+impl TileAddressing {
+    pub fn from_lcdc(lcdc: u8) -> Self {
+        if lcdc & 0x10 != 0 { Self::Unsigned } else { Self::Signed }
+    }
+
+    /// Offset of tile `index`'s data from the start of a VRAM bank.
+    pub fn offset(self, index: u8) -> usize {
+        match self {
+            Self::Unsigned => index as usize * TILE_SIZE,
+            Self::Signed => (2 * BLOCK_SIZE).wrapping_add_signed(index as i8 as isize * TILE_SIZE as isize),
+        }
+    }
+}
+
+/// A CGB BG map attribute byte, kept in bank 1 at the same offset as the
+/// tile index it applies to. Always zero on DMG.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BgAttributes(pub u8);
+
+impl BgAttributes {
+    pub fn palette(self) -> usize {
+        (self.0 & 0x07) as usize
+    }
+
+    pub fn bank(self) -> usize {
+        (self.0 >> 3 & 1) as usize
+    }
+
+    pub fn flip_x(self) -> bool {
+        self.0 & 0x20 != 0
+    }
+
+    pub fn flip_y(self) -> bool {
+        self.0 & 0x40 != 0
+    }
+
+    /// Whether BG colours 1–3 draw over objects.
+    pub fn priority(self) -> bool {
+        self.0 & 0x80 != 0
+    }
+}
+
+/// A tilemap cell resolved to its tile. `tile` is as stored; the flips in
+/// `attributes` are not applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapEntry {
+    pub index: u8,
+    pub tile: Tile,
+    pub attributes: BgAttributes,
+}
+
+/// Both VRAM banks. Bank 1 only exists on CGB, where it holds a second set
+/// of tiles and, at the tilemap offsets, the BG attribute maps.
+pub struct VRam<'mem>([&'mem [u8]; 2]);
+
+pub fn as_vram<'mem>(bank0: &'mem [u8], bank1: &'mem [u8]) -> VRam<'mem> {
+    VRam([bank0, bank1])
 }
 
 impl<'mem> VRam<'mem> {
-    pub fn block1(&self) -> Block {
+    pub fn bank(&self, bank: usize) -> VRamBank<'mem> {
+        VRamBank(self.0[bank & 1])
+    }
+
+    pub fn block1(&self) -> Block<'mem> {
+        self.bank(0).block1()
+    }
+
+    pub fn block2(&self) -> Block<'mem> {
+        self.bank(0).block2()
+    }
+
+    pub fn block3(&self) -> Block<'mem> {
+        self.bank(0).block3()
+    }
+
+    pub fn tilemap_0(&self) -> Tilemap<'mem> {
+        self.bank(0).tilemap_0()
+    }
+
+    pub fn tilemap_1(&self) -> Tilemap<'mem> {
+        self.bank(0).tilemap_1()
+    }
+
+    /// The map at 0x9800 when `map` is 0, 0x9C00 when it is 1, as LCDC
+    /// bits 3 and 6 select.
+    pub fn tilemap(&self, map: usize) -> Tilemap<'mem> {
+        if map & 1 == 0 { self.tilemap_0() } else { self.tilemap_1() }
+    }
+
+    /// Resolves cell (`x`, `y`) of `map` to its tile data. Attributes are
+    /// only read on CGB, where they can move the tile to bank 1.
+    pub fn map_entry(&self, map: usize, x: usize, y: usize, addressing: TileAddressing, cgb: bool) -> MapEntry {
+        let index = self.tilemap(map).tile_index(x, y);
+        let attributes = if cgb {
+            self.bank(1).tilemap(map).attributes(x, y)
+        } else {
+            BgAttributes::default()
+        };
+        let tile = self.bank(attributes.bank()).tile(addressing, index);
+        MapEntry { index, tile, attributes }
+    }
+
+    /// BG attributes for the cells of `tilemap_0`.
+    pub fn attributes_0(&self) -> Tilemap<'mem> {
+        self.bank(1).tilemap_0()
+    }
+
+    /// BG attributes for the cells of `tilemap_1`.
+    pub fn attributes_1(&self) -> Tilemap<'mem> {
+        self.bank(1).tilemap_1()
+    }
+}
+
+/// One 8 KiB VRAM bank.
+pub struct VRamBank<'mem>(&'mem [u8]);
+
+impl<'mem> VRamBank<'mem> {
+    pub fn block1(&self) -> Block<'mem> {
         Block(&self.0[0..BLOCK_SIZE])
     }
 
-    pub fn block2(&self) -> Block {
+    pub fn block2(&self) -> Block<'mem> {
         Block(&self.0[BLOCK_SIZE..2*BLOCK_SIZE])
     }
 
-    pub fn block3(&self) -> Block {
+    pub fn block3(&self) -> Block<'mem> {
         Block(&self.0[2*BLOCK_SIZE..3*BLOCK_SIZE])
     }
 
-    pub fn tilemap_0(&self) -> Tilemap {
+    pub fn tilemap_0(&self) -> Tilemap<'mem> {
         Tilemap(&self.0[3*BLOCK_SIZE..3*BLOCK_SIZE+TILEMAP_SIZE])
     }
 
-    pub fn tilemap_1(&self) -> Tilemap {
+    pub fn tilemap_1(&self) -> Tilemap<'mem> {
         Tilemap(&self.0[3*BLOCK_SIZE+TILEMAP_SIZE..3*BLOCK_SIZE+2*TILEMAP_SIZE])
     }
+
+    pub fn tilemap(&self, map: usize) -> Tilemap<'mem> {
+        if map & 1 == 0 { self.tilemap_0() } else { self.tilemap_1() }
+    }
+
+    pub fn tile(&self, addressing: TileAddressing, index: u8) -> Tile {
+        let offset = addressing.offset(index);
+        Tile::decode(&self.0[offset..offset + TILE_SIZE])
+    }
+
+    /// All 384 tiles of the bank, from 0x8000 up.
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> + use<'mem> {
+        Block(&self.0[..3*BLOCK_SIZE]).tiles()
+    }
 }
 
-struct Block<'mem>(&'mem [u8]);
+/// 128 tiles' worth of tile data, or the whole tile area of a bank when
+/// iterating over a `VRamBank`.
+pub struct Block<'mem>(&'mem [u8]);
 
-struct Tilemap<'mem>(&'mem [u8]);
+impl<'mem> Block<'mem> {
+    pub fn tile(&self, index: usize) -> Tile {
+        Tile::decode(&self.0[index * TILE_SIZE..(index + 1) * TILE_SIZE])
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> + use<'mem> {
+        self.0.chunks_exact(TILE_SIZE).map(Tile::decode)
+    }
+}
+
+/// A 32×32 map of tile indices, or of CGB attributes when taken from bank 1.
+pub struct Tilemap<'mem>(&'mem [u8]);
 
+impl Tilemap<'_> {
+    /// The byte for cell (`x`, `y`), wrapping around both edges.
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.0[(y % TILEMAP_WIDTH) * TILEMAP_WIDTH + x % TILEMAP_WIDTH]
+    }
+
+    pub fn tile_index(&self, x: usize, y: usize) -> u8 {
+        self.get(x, y)
+    }
+
+    pub fn attributes(&self, x: usize, y: usize) -> BgAttributes {
+        BgAttributes(self.get(x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bank where the top row of tile `i` is `i` in the low bit plane.
+    fn numbered_bank() -> Vec<u8> {
+        let mut bank = vec![0; VRAM_BANK_SIZE];
+        for i in 0..3 * TILES_PER_BLOCK {
+            bank[i * TILE_SIZE] = i as u8;
+        }
+        bank
+    }
+
+    fn number(tile: Tile) -> u8 {
+        tile.row(0).iter().fold(0, |n, &colour| n << 1 | colour)
+    }
+
+    #[test]
+    fn block_tiles_are_indexed_from_the_block_start() {
+        let bank = numbered_bank();
+        let bank = VRamBank(&bank);
+        assert_eq!(bank.block2().tiles().count(), TILES_PER_BLOCK);
+        assert_eq!(bank.block2().tiles().map(number).nth(5), Some(0x85));
+        assert_eq!(number(bank.block2().tile(5)), 0x85);
+        assert_eq!(number(bank.block3().tile(0)), 0x00);
+
+        assert_eq!(bank.tiles().count(), 3 * TILES_PER_BLOCK);
+        assert_eq!(bank.tiles().nth(130), Some(bank.block2().tile(2)));
+    }
+
+    #[test]
+    fn signed_addressing_centres_on_0x9000() {
+        let signed = TileAddressing::from_lcdc(0x00);
+        assert_eq!(signed, TileAddressing::Signed);
+        assert_eq!(signed.offset(0x00), 0x1000);
+        assert_eq!(signed.offset(0x7F), 0x17F0);
+        assert_eq!(signed.offset(0x80), 0x0800);
+        assert_eq!(signed.offset(0xFF), 0x0FF0);
+
+        let bank = numbered_bank();
+        let bank = VRamBank(&bank);
+        assert_eq!(number(bank.tile(signed, 0x00)), 0x00);
+        assert_eq!(number(bank.tile(signed, 0xFF)), 0xFF);
+        assert_eq!(bank.tile(signed, 0x80), bank.tile(TileAddressing::from_lcdc(0x10), 0x80));
+    }
+
+    #[test]
+    fn decodes_cgb_attributes() {
+        let attributes = BgAttributes(0xAD);
+        assert_eq!(attributes.palette(), 5);
+        assert_eq!(attributes.bank(), 1);
+        assert!(attributes.flip_x());
+        assert!(!attributes.flip_y());
+        assert!(attributes.priority());
+
+        let attributes = BgAttributes(0x42);
+        assert_eq!(attributes.palette(), 2);
+        assert_eq!(attributes.bank(), 0);
+        assert!(!attributes.flip_x());
+        assert!(attributes.flip_y());
+        assert!(!attributes.priority());
+    }
+
+    #[test]
+    fn cgb_attributes_pick_the_tile_bank() {
+        let mut bank0 = numbered_bank();
+        bank0[3 * BLOCK_SIZE + 1] = 0x01;
+        let mut bank1 = vec![0; VRAM_BANK_SIZE];
+        bank1[0x10] = 0x0F;
+        bank1[3 * BLOCK_SIZE + 1] = 0x08;
+        let vram = as_vram(&bank0, &bank1);
+
+        let entry = vram.map_entry(0, 1, 0, TileAddressing::Unsigned, true);
+        assert_eq!(entry.attributes.bank(), 1);
+        assert_eq!(number(entry.tile), 0x0F);
+
+        let entry = vram.map_entry(0, 1, 0, TileAddressing::Unsigned, false);
+        assert_eq!(entry.attributes, BgAttributes::default());
+        assert_eq!(number(entry.tile), 0x01);
+    }
+}
@@ -0,0 +1,18 @@
+/// The hardware being emulated, for behaviour that differs between models.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Model {
+    Dmg,
+    /// CGB revisions 0 through C.
+    CgbC,
+    CgbD,
+    #[default]
+    CgbE,
+    /// A GBA running Game Boy software.
+    Agb,
+}
+
+impl Model {
+    pub fn is_cgb(self) -> bool {
+        self != Model::Dmg
+    }
+}
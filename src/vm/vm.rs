@@ -1,4 +1,5 @@
-use crate::memory::Memory;
+use crate::memory::{AccessEvent, Memory};
+use crate::model::Model;
 use crate::vm::op::{ Op, R8, R16, R16mem, R16Stk };
 
 pub struct VM {
@@ -11,8 +12,30 @@ impl VM {
         Self { registers: Registers::default() }
     }
     
+    /// Loads the registers the boot ROM of `model` leaves behind, for
+    /// starting at the cartridge entry point without one.
+    pub fn skip_boot(&mut self, model: Model) {
+        let (af, bc, de, hl) = match model {
+            Model::Dmg => (0x01B0, 0x0013, 0x00D8, 0x014D),
+            Model::Agb => (0x1100, 0x0100, 0xFF56, 0x000D),
+            _ => (0x1180, 0x0000, 0xFF56, 0x000D),
+        };
+        self.registers = Registers { af, bc, de, hl, sp: 0xFFFE, pc: 0x0100 };
+    }
+
+    /// Executes instructions until a pausing hook fires, returning the
+    /// access that tripped it.
+    pub fn run(&mut self, memory: &mut Memory) -> AccessEvent {
+        loop {
+            self.execute(memory);
+            if let Some(event) = memory.take_pause() {
+                return event;
+            }
+        }
+    }
+
     pub fn execute(&mut self, memory: &mut Memory) {
-        let op: Op = memory.read_byte(self.registers.pc).into();
+        let op: Op = memory.fetch_byte(self.registers.pc).into();
         self.registers.pc += 1;
 
         match op {
@@ -48,7 +71,8 @@ impl VM {
 
             Op::IncR8{ op } => {
                 if let R8::HLref = op {
-                    memory[self.registers.hl] += 1;
+                    let byte = memory.read_byte(self.registers.hl);
+                    memory.write_byte(self.registers.hl, byte.wrapping_add(1));
                 } else {
                     self.inc_r8(op);
                 }
@@ -56,7 +80,8 @@ impl VM {
 
             Op::DecR8{ op } => {
                 if op == R8::HLref {
-                    memory[self.registers.hl] -= 1;
+                    let byte = memory.read_byte(self.registers.hl);
+                    memory.write_byte(self.registers.hl, byte.wrapping_sub(1));
                 } else {
                     self.dec_r8(op);
                 }
@@ -66,7 +91,7 @@ impl VM {
                 let imm8 = memory.read_byte(self.registers.pc);
                 self.registers.pc += 1;
                 if dst == R8::HLref {
-                    memory[self.registers.hl] = imm8;
+                    memory.write_byte(self.registers.hl, imm8);
                 } else {
                     self.set_r8(dst, imm8);
                 }
@@ -482,3 +507,22 @@ const F_ZERO: Flags = 0x40;
 const F_SUBTRACTION: Flags = 0x20;
 const F_HALF_CARRY: Flags = 0x10;
 const F_CARRY: Flags = 0x08;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{self, Access, Hook};
+
+    #[test]
+    fn run_stops_after_the_instruction_that_paused() {
+        // Work RAM starts out zeroed, so this runs NOPs from 0xC000.
+        let mut mem = memory::new();
+        let mut vm = VM::new();
+        vm.registers.pc = 0xC000;
+        mem.add_hook(Hook::pause(0xC003..=0xC003, Access::Execute));
+
+        let event = vm.run(&mut mem);
+        assert_eq!(event, AccessEvent { addr: 0xC003, access: Access::Execute, value: 0x00 });
+        assert_eq!(vm.registers.pc, 0xC004);
+    }
+}